@@ -6,9 +6,15 @@
 with lib; let
   cfg = config.services.rss-webhook-trigger;
   format = pkgs.formats.toml { };
-  configFile = format.generate "trigger.toml" {
-    feed = cfg.hooks;
-  };
+  configFile = format.generate "trigger.toml" ({
+    feed = map (filterAttrs (_: value: value != null)) cfg.hooks;
+  } // optionalAttrs (cfg.mqtt != null) {
+    inherit (cfg) mqtt;
+  } // optionalAttrs (cfg.http != { }) {
+    inherit (cfg) http;
+  } // optionalAttrs (cfg.history != null) {
+    inherit (cfg) history;
+  });
 in
 {
   options.services.rss-webhook-trigger = {
@@ -17,13 +23,16 @@ in
     hooks = mkOption rec {
       description = "Hook configuration";
       type = types.listOf (types.submodule {
+        # allow any of the other per-feed options from the configuration file
+        freeformType = format.type;
         options = {
           feed = mkOption {
             type = types.str;
             description = "Source feed";
           };
           hook = mkOption {
-            type = types.str;
+            type = types.nullOr types.str;
+            default = null;
             description = "hook url";
           };
           headers = mkOption {
@@ -36,16 +45,51 @@ in
             default = { };
             description = "body to send";
           };
+          signature = mkOption {
+            type = types.nullOr types.attrs;
+            default = null;
+            description = "hmac signature options";
+          };
+          action = mkOption {
+            type = types.listOf types.attrs;
+            default = [ ];
+            description = "additional actions to trigger, such as chat notifications";
+          };
         };
       });
     };
 
+    mqtt = mkOption {
+      type = types.nullOr types.attrs;
+      default = null;
+      description = "mqtt broker used by mqtt actions";
+    };
+
+    http = mkOption {
+      type = types.attrs;
+      default = { };
+      description = "http client settings such as timeouts, proxy and certificates";
+    };
+
+    history = mkOption {
+      type = types.nullOr types.attrs;
+      default = null;
+      example = { path = "/var/lib/rss-webhook-trigger/history.jsonl"; max_entries = 1000; };
+      description = "where to record the trigger history, the service can write to /var/lib/rss-webhook-trigger";
+    };
+
     log = mkOption {
       type = types.str;
       default = "INFO";
       description = "Log level";
     };
 
+    logFormat = mkOption {
+      type = types.enum [ "text" "json" "journald" ];
+      default = "text";
+      description = "Log output format";
+    };
+
     package = mkOption {
       type = types.package;
       description = "package to use";
@@ -61,8 +105,9 @@ in
       };
 
       serviceConfig = {
-        ExecStart = "${cfg.package}/bin/rss-webhook-trigger ${configFile}";
+        ExecStart = "${cfg.package}/bin/rss-webhook-trigger --log-format ${cfg.logFormat} ${configFile}";
         Restart = "on-failure";
+        StateDirectory = "rss-webhook-trigger";
         DynamicUser = true;
         PrivateTmp = true;
         ProtectSystem = "strict";
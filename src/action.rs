@@ -0,0 +1,211 @@
+use crate::change::ChangeEvent;
+use crate::config::{ActionConfig, ActionKind, WebhookConfig};
+use crate::error::ActionError;
+use crate::exec;
+use crate::item::FeedItem;
+use crate::mqtt::MqttClient;
+use crate::secret::{redact, RedactedRequest};
+use crate::signature::sign;
+use crate::template::render_json;
+use crate::{ci, sink, smtp};
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use reqwest::{Client, RequestBuilder, Url};
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{debug, instrument};
+
+/// Shared state used by the actions
+pub struct ActionContext {
+    pub client: Client,
+    pub mqtt: Option<MqttClient>,
+    pub actions: ActionRegistry,
+}
+
+/// Summary of a request send by an action
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// method and url of the request, with secrets removed
+    pub request: String,
+    pub status: u16,
+}
+
+/// Something that can be triggered when a feed has new items
+#[async_trait]
+pub trait Action: Send + Sync {
+    /// Trigger the action for a change, the new items are ordered newest first
+    ///
+    /// Returns the last request send, for actions that send http requests.
+    async fn trigger(
+        &self,
+        context: &ActionContext,
+        change: &ChangeEvent,
+    ) -> Result<Option<Delivery>, ActionError>;
+}
+
+/// Maps names to the custom actions that can be configured with `type = "custom"`
+#[derive(Clone, Default)]
+pub struct ActionRegistry {
+    actions: HashMap<String, Arc<dyn Action>>,
+}
+
+impl ActionRegistry {
+    /// Register an action by name, replacing any existing action with the name
+    pub fn register<A: Action + 'static>(&mut self, name: &str, action: A) {
+        self.actions.insert(name.into(), Arc::new(action));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Action> {
+        self.actions.get(name).map(Arc::as_ref)
+    }
+}
+
+/// Trigger a configured action with the items selected by the action config
+#[instrument(skip_all, fields(feed = change.feed, action = action.kind()))]
+pub async fn trigger(
+    context: &ActionContext,
+    action: &ActionConfig,
+    change: &ChangeEvent,
+) -> Result<Option<Delivery>, ActionError> {
+    let change = change.with_items(action.items.select(&change.items));
+    debug!(items = change.items.len(), "Triggering action");
+    action.kind.trigger(context, &change).await
+}
+
+#[async_trait]
+impl Action for ActionKind {
+    async fn trigger(
+        &self,
+        context: &ActionContext,
+        change: &ChangeEvent,
+    ) -> Result<Option<Delivery>, ActionError> {
+        let feed = change.feed.as_str();
+        let items = change.items.as_slice();
+        match self {
+            ActionKind::Webhook(hook) => webhook(&context.client, hook, change).await.map(Some),
+            ActionKind::Exec(exec) => exec::run(exec, feed, items).await.map(|_| None),
+            ActionKind::Smtp(smtp) => smtp::send(smtp, feed, items).await.map(|_| None),
+            ActionKind::Mqtt(mqtt) => context
+                .mqtt
+                .as_ref()
+                .ok_or(ActionError::MqttNotConfigured)?
+                .publish(mqtt, change)
+                .map(|_| None),
+            // ci pipelines are started once for the newest item
+            ActionKind::Github(github) => ci::github(&context.client, github, feed, newest(items)?)
+                .await
+                .map(Some),
+            ActionKind::Gitlab(gitlab) => ci::gitlab(&context.client, gitlab, feed, newest(items)?)
+                .await
+                .map(Some),
+            ActionKind::Woodpecker(woodpecker) => {
+                ci::woodpecker(&context.client, woodpecker, feed, newest(items)?)
+                    .await
+                    .map(Some)
+            }
+            ActionKind::Forgejo(forgejo) => {
+                ci::forgejo(&context.client, forgejo, feed, newest(items)?)
+                    .await
+                    .map(Some)
+            }
+            ActionKind::Custom(custom) => {
+                context
+                    .actions
+                    .get(&custom.name)
+                    .ok_or_else(|| ActionError::UnknownAction(custom.name.clone()))?
+                    .trigger(context, change)
+                    .await
+            }
+            // notifications are sent for every new item
+            action => {
+                let mut delivery = None;
+                for item in items {
+                    delivery = notify(&context.client, action, item).await?;
+                }
+                Ok(delivery)
+            }
+        }
+    }
+}
+
+async fn notify(
+    client: &Client,
+    action: &ActionKind,
+    item: &FeedItem,
+) -> Result<Option<Delivery>, ActionError> {
+    let delivery = match action {
+        ActionKind::Discord(discord) => sink::discord(client, discord, item).await?,
+        ActionKind::Slack(slack) => sink::slack(client, slack, item).await?,
+        ActionKind::Matrix(matrix) => sink::matrix(client, matrix, item).await?,
+        ActionKind::Ntfy(ntfy) => sink::ntfy(client, ntfy, item).await?,
+        ActionKind::Gotify(gotify) => sink::gotify(client, gotify, item).await?,
+        _ => return Ok(None),
+    };
+    Ok(Some(delivery))
+}
+
+fn newest(items: &[FeedItem]) -> Result<&FeedItem, ActionError> {
+    items.first().ok_or(ActionError::NoItems)
+}
+
+async fn webhook(
+    client: &Client,
+    hook: &WebhookConfig,
+    change: &ChangeEvent,
+) -> Result<Delivery, ActionError> {
+    let mut req = client.post(&hook.hook);
+    for (key, value) in &hook.headers {
+        req = req.header(key, value);
+    }
+    // serialize the body ourselves so the signature covers the exact bytes being sent
+    let body = if hook.body.is_null() {
+        Vec::new()
+    } else {
+        req = req.header(CONTENT_TYPE, "application/json");
+        let body = if hook.template {
+            render_json(&hook.body, change)
+        } else {
+            hook.body.clone()
+        };
+        serde_json::to_vec(&body).expect("json values can always be serialized")
+    };
+    if let Some(signature) = &hook.signature {
+        let timestamp = signature
+            .timestamp()
+            .then(|| OffsetDateTime::now_utc().unix_timestamp());
+        if let Some(timestamp) = timestamp {
+            req = req.header(&signature.timestamp_header, timestamp);
+        }
+        req = req.header(
+            &signature.header,
+            sign(signature.secret.expose(), timestamp, &body),
+        );
+    }
+    if !body.is_empty() {
+        req = req.body(body);
+    }
+    send(req).await
+}
+
+pub async fn send(req: RequestBuilder) -> Result<Delivery, ActionError> {
+    let (client, req) = req.header(USER_AGENT, "rss-webhook-trigger").build_split();
+    let req = req?;
+    debug!(request = ?RedactedRequest(&req), "sending trigger request");
+    let request = format!("{} {}", req.method(), redact(req.url().as_str()));
+    let response = client.execute(req).await?.error_for_status()?;
+    Ok(Delivery {
+        request,
+        status: response.status().as_u16(),
+    })
+}
+
+/// Append path segments to a base url, each segment is percent-encoded
+pub fn api_url(base: &str, segments: &[&str]) -> Result<Url, ActionError> {
+    let mut url = Url::parse(base)?;
+    url.path_segments_mut()
+        .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
@@ -0,0 +1,173 @@
+use crate::item::FeedItem;
+use crate::key::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Whether items were added to the feed or existing items were edited
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    #[default]
+    Added,
+    Updated,
+}
+
+impl ChangeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Updated => "updated",
+        }
+    }
+}
+
+/// A detected change in a feed, passed on to the actions
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    /// name of the source the feed was fetched from
+    pub source: &'static str,
+    pub feed: String,
+    /// new or edited items, newest first
+    pub items: Vec<FeedItem>,
+    /// key of the feed before the change, not set for changes found when first fetching the feed
+    pub old_key: Option<Key>,
+    pub new_key: Key,
+    /// changed tags, for docker hub repositories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<TagDiff>,
+}
+
+impl ChangeEvent {
+    /// Copy of the change with only the given items
+    pub fn with_items(&self, items: &[FeedItem]) -> Self {
+        ChangeEvent {
+            kind: self.kind,
+            source: self.source,
+            feed: self.feed.clone(),
+            items: items.to_vec(),
+            old_key: self.old_key,
+            new_key: self.new_key,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Combine with a later change of the same feed, as if both happened at once
+    pub fn merge(&mut self, newer: ChangeEvent) {
+        let older = std::mem::replace(&mut self.items, newer.items);
+        for item in older {
+            if !self.items.iter().any(|new| new.key == item.key) {
+                self.items.push(item);
+            }
+        }
+        self.new_key = newer.new_key;
+        self.tags = match (self.tags.take(), newer.tags) {
+            (Some(mut tags), Some(newer)) => {
+                tags.merge(newer);
+                Some(tags)
+            }
+            (tags, newer) => newer.or(tags),
+        };
+    }
+}
+
+/// Tags that were added, moved to a different image or removed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagDiff {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TagDiff {
+    /// Compare two sets of tags, identified by name with a key for the tagged image
+    pub fn between(old: &HashMap<String, Key>, new: &HashMap<String, Key>) -> Self {
+        let mut diff = TagDiff::default();
+        for (name, key) in new {
+            match old.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(old_key) if old_key != key => diff.updated.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .keys()
+            .filter(|name| !new.contains_key(*name))
+            .cloned()
+            .collect();
+        diff.added.sort();
+        diff.updated.sort();
+        diff.removed.sort();
+        diff
+    }
+
+    /// Combine with the diff of a later change
+    pub fn merge(&mut self, newer: TagDiff) {
+        for name in newer.added {
+            // removed and added again is the same tag pointing to a new image
+            if let Some(index) = self.removed.iter().position(|removed| *removed == name) {
+                self.removed.remove(index);
+                self.updated.push(name);
+            } else {
+                self.added.push(name);
+            }
+        }
+        for name in newer.updated {
+            if !self.added.contains(&name) && !self.updated.contains(&name) {
+                self.updated.push(name);
+            }
+        }
+        for name in newer.removed {
+            if let Some(index) = self.added.iter().position(|added| *added == name) {
+                self.added.remove(index);
+            } else {
+                self.updated.retain(|updated| *updated != name);
+                self.removed.push(name);
+            }
+        }
+        self.added.sort();
+        self.updated.sort();
+        self.removed.sort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[(&str, &str)]) -> HashMap<String, Key> {
+        tags.iter()
+            .map(|(name, image)| (name.to_string(), Key::builder("test").field(image).finish()))
+            .collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tag_diff() {
+        let old = tags(&[("latest", "a"), ("1.0", "a"), ("0.9", "b")]);
+        let new = tags(&[("latest", "c"), ("1.0", "a"), ("1.1", "c")]);
+        assert_eq!(
+            TagDiff {
+                added: names(&["1.1"]),
+                updated: names(&["latest"]),
+                removed: names(&["0.9"]),
+            },
+            TagDiff::between(&old, &new)
+        );
+    }
+
+    #[test]
+    fn test_tag_diff_merge() {
+        let first = tags(&[("latest", "a"), ("1.0", "a")]);
+        let second = tags(&[("latest", "b"), ("1.1", "b")]);
+        let third = tags(&[("latest", "c"), ("1.0", "c")]);
+        let mut diff = TagDiff::between(&first, &second);
+        diff.merge(TagDiff::between(&second, &third));
+        assert_eq!(TagDiff::between(&first, &third), diff);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::action::{api_url, send, Delivery};
+use crate::config::{ForgejoConfig, GithubConfig, GitlabConfig, WoodpeckerConfig};
+use crate::error::ActionError;
+use crate::item::FeedItem;
+use crate::template::render;
+use reqwest::header::ACCEPT;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub async fn github(
+    client: &Client,
+    config: &GithubConfig,
+    feed: &str,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let (owner, repo) = split_repository(&config.repository)?;
+    let inputs = render_all(&config.inputs, feed, item);
+    let req = match &config.workflow {
+        Some(workflow) => client
+            .post(api_url(
+                &config.api,
+                &[
+                    "repos",
+                    owner,
+                    repo,
+                    "actions",
+                    "workflows",
+                    workflow,
+                    "dispatches",
+                ],
+            )?)
+            // workflows reject undeclared inputs, so only the configured inputs are send
+            .json(&json!({
+                "ref": config.git_ref,
+                "inputs": inputs,
+            })),
+        None => {
+            let mut payload = item_details(feed, item);
+            payload.extend(
+                inputs
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value))),
+            );
+            client
+                .post(api_url(&config.api, &["repos", owner, repo, "dispatches"])?)
+                .json(&json!({
+                    "event_type": config.event_type,
+                    "client_payload": payload,
+                }))
+        }
+    };
+    send(
+        req.bearer_auth(config.token.expose())
+            .header(ACCEPT, "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28"),
+    )
+    .await
+}
+
+pub async fn gitlab(
+    client: &Client,
+    config: &GitlabConfig,
+    feed: &str,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let variables = with_item_variables(render_all(&config.variables, feed, item), feed, item);
+    let mut form = vec![
+        ("token".to_string(), config.token.expose().to_string()),
+        ("ref".to_string(), config.git_ref.clone()),
+    ];
+    form.extend(
+        variables
+            .into_iter()
+            .map(|(key, value)| (format!("variables[{}]", key), value)),
+    );
+    let url = api_url(
+        &config.server,
+        &[
+            "api",
+            "v4",
+            "projects",
+            &config.project,
+            "trigger",
+            "pipeline",
+        ],
+    )?;
+    send(client.post(url).form(&form)).await
+}
+
+pub async fn woodpecker(
+    client: &Client,
+    config: &WoodpeckerConfig,
+    feed: &str,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let variables = with_item_variables(render_all(&config.variables, feed, item), feed, item);
+    let url = api_url(
+        &config.server,
+        &["api", "repos", &config.repo_id.to_string(), "pipelines"],
+    )?;
+    send(
+        client
+            .post(url)
+            .bearer_auth(config.token.expose())
+            .json(&json!({
+                "branch": config.branch,
+                "variables": variables,
+            })),
+    )
+    .await
+}
+
+pub async fn forgejo(
+    client: &Client,
+    config: &ForgejoConfig,
+    feed: &str,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let (owner, repo) = split_repository(&config.repository)?;
+    let url = api_url(
+        &config.server,
+        &[
+            "api",
+            "v1",
+            "repos",
+            owner,
+            repo,
+            "actions",
+            "workflows",
+            &config.workflow,
+            "dispatches",
+        ],
+    )?;
+    send(
+        client
+            .post(url)
+            .header("Authorization", format!("token {}", config.token.expose()))
+            .json(&json!({
+                "ref": config.git_ref,
+                "inputs": render_all(&config.inputs, feed, item),
+            })),
+    )
+    .await
+}
+
+fn split_repository(repository: &str) -> Result<(&str, &str), ActionError> {
+    repository
+        .split_once('/')
+        .ok_or_else(|| ActionError::InvalidRepository(repository.into()))
+}
+
+fn render_all(
+    templates: &HashMap<String, String>,
+    feed: &str,
+    item: &FeedItem,
+) -> HashMap<String, String> {
+    templates
+        .iter()
+        .map(|(key, template)| (key.clone(), render(template, feed, item)))
+        .collect()
+}
+
+fn item_details(feed: &str, item: &FeedItem) -> Map<String, Value> {
+    let mut details = Map::new();
+    details.insert("feed".into(), feed.into());
+    details.insert("version".into(), item.display_title().into());
+    if let Some(link) = &item.link {
+        details.insert("link".into(), link.as_str().into());
+    }
+    details
+}
+
+/// Add the item details as `RSS_*` variables, configured variables take precedence
+fn with_item_variables(
+    mut variables: HashMap<String, String>,
+    feed: &str,
+    item: &FeedItem,
+) -> HashMap<String, String> {
+    let defaults = [
+        ("RSS_FEED", feed),
+        ("RSS_ITEM_TITLE", item.display_title()),
+        ("RSS_ITEM_LINK", item.link.as_deref().unwrap_or_default()),
+    ];
+    for (key, value) in defaults {
+        variables.entry(key.into()).or_insert_with(|| value.into());
+    }
+    variables
+}
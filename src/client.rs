@@ -0,0 +1,118 @@
+use crate::config::{Config, HttpConfig};
+use crate::error::ClientError;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Identity, Proxy};
+use std::collections::HashMap;
+use std::fs::read;
+use std::path::Path;
+
+/// Http clients for all distinct http settings used in the config
+pub struct ClientPool {
+    default: Client,
+    clients: HashMap<HttpConfig, Client>,
+}
+
+impl ClientPool {
+    /// Build the clients for the global settings and all feed specific settings
+    pub fn new(config: &Config) -> Result<Self, ClientError> {
+        let default = build_client(&config.http)?;
+        let mut clients = HashMap::new();
+        clients.insert(config.http.clone(), default.clone());
+        for feed in &config.feed {
+            if !clients.contains_key(&feed.http) {
+                clients.insert(feed.http.clone(), build_client(&feed.http)?);
+            }
+        }
+        Ok(ClientPool { default, clients })
+    }
+
+    /// The client using the global http settings
+    pub fn default_client(&self) -> &Client {
+        &self.default
+    }
+
+    pub fn get(&self, config: &HttpConfig) -> &Client {
+        self.clients.get(config).unwrap_or(&self.default)
+    }
+}
+
+fn build_client(config: &HttpConfig) -> Result<Client, ClientError> {
+    let mut builder = Client::builder()
+        .connect_timeout(config.connect_timeout())
+        .timeout(config.timeout())
+        .redirect(Policy::limited(config.max_redirects()));
+    if let Some(read_timeout) = config.read_timeout() {
+        builder = builder.read_timeout(read_timeout);
+    }
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(Proxy::all(proxy).map_err(ClientError::Proxy)?);
+    }
+    for path in config.ca_certificates.iter().flatten() {
+        let certificate =
+            Certificate::from_pem(&read_file(path)?).map_err(|error| ClientError::Certificate {
+                error,
+                path: path.into(),
+            })?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    match (&config.client_certificate, &config.client_key) {
+        (Some(certificate), Some(key)) => {
+            let mut pem = read_file(certificate)?;
+            pem.push(b'\n');
+            pem.extend(read_file(key)?);
+            let identity = Identity::from_pem(&pem).map_err(|error| ClientError::Certificate {
+                error,
+                path: certificate.into(),
+            })?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err(ClientError::IncompleteIdentity),
+    }
+    builder.build().map_err(ClientError::Build)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, ClientError> {
+    read(path).map_err(|error| ClientError::Read {
+        error,
+        path: path.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_clients() {
+        let config = Config::from_toml(
+            r#"
+            [http]
+            timeout = 30
+
+            [[feed]]
+            feed = "https://example.com/a.xml"
+            hook = "https://example.com/hook"
+
+            [[feed]]
+            feed = "https://example.com/b.xml"
+            hook = "https://example.com/hook"
+            http = { timeout = 30 }
+
+            [[feed]]
+            feed = "https://example.com/c.xml"
+            hook = "https://example.com/hook"
+            http = { timeout = 5 }
+            "#,
+        )
+        .unwrap();
+        let pool = ClientPool::new(&config).unwrap();
+        let [a, b, c] = [0, 1, 2].map(|index| pool.get(&config.feed[index].http));
+
+        // identical settings, whether inherited or set for the feed, use the same client
+        assert!(std::ptr::eq(pool.get(&config.http), a));
+        assert!(std::ptr::eq(a, b));
+        assert!(!std::ptr::eq(a, c));
+        assert_eq!(2, pool.clients.len());
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Local, TimeDelta, TimeZone};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// Source of the current time
+///
+/// The clock follows the system time, but can be moved forward to simulate the passing of time in tests.
+/// Clones share the same offset.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl Clock {
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+
+    fn offset(&self) -> Duration {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn now(&self) -> Instant {
+        Instant::now() + self.offset()
+    }
+
+    pub fn now_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc() + self.offset()
+    }
+
+    pub fn now_local(&self) -> DateTime<Local> {
+        Local::now() + TimeDelta::from_std(self.offset()).unwrap_or_default()
+    }
+
+    /// Convert a wall clock time into an `Instant`, times in the past are mapped to now
+    pub fn instant_at<Tz: TimeZone>(&self, time: DateTime<Tz>) -> Instant {
+        self.now()
+            + (time.with_timezone(&Local) - self.now_local())
+                .to_std()
+                .unwrap_or_default()
+    }
+}
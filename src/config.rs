@@ -1,29 +1,850 @@
+use crate::change::ChangeKind;
 use crate::error::ConfigError;
+use crate::fetcher::FetchSchedule;
+use crate::item::FeedItem;
+use crate::key::Key;
+use crate::schedule::QuietWindow;
+use crate::secret::{self, Secret};
+use croner::Cron;
 use reqwest::header::{HeaderValue, InvalidHeaderValue};
+use reqwest::{RequestBuilder, Url};
+use rumqttc::{qos, QoS};
 use secretfile::{load, SecretError};
 use serde::de::Error;
 use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Debug, Formatter};
 use std::fs::read_to_string;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use time::OffsetDateTime;
 use tokio::time::Duration;
+use tracing::debug;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     interval: Option<u64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+    /// http settings for fetching feeds and triggering actions, feeds can override these
+    #[serde(default)]
+    pub http: HttpConfig,
+    pub mqtt: Option<MqttConfig>,
+    #[serde(default)]
+    pub log: LogConfig,
+    pub history: Option<HistoryConfig>,
+    pub status: Option<StatusConfig>,
     pub feed: Vec<FeedConfig>,
 }
 
+/// Where to record triggered actions
+#[derive(Debug, Deserialize)]
+pub struct HistoryConfig {
+    pub path: PathBuf,
+    #[serde(default = "default_history_entries")]
+    pub max_entries: usize,
+    /// maximum age of entries in days
+    max_age: Option<u64>,
+}
+
+impl HistoryConfig {
+    pub fn max_age(&self) -> Option<time::Duration> {
+        self.max_age.map(|days| {
+            time::Duration::seconds(
+                i64::try_from(days)
+                    .unwrap_or(i64::MAX)
+                    .saturating_mul(24 * 60 * 60),
+            )
+        })
+    }
+}
+
+fn default_history_entries() -> usize {
+    1000
+}
+
+/// Http endpoint for querying the state of the trigger
+#[derive(Debug, Deserialize)]
+pub struct StatusConfig {
+    pub listen: SocketAddr,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// default log level or filter directive, `RUST_LOG` takes precedence
+    pub level: Option<String>,
+    /// log level per module, such as `{ hyper = "warn" }`
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+    Journald,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            "journald" => Ok(LogFormat::Journald),
+            _ => Err(format!(
+                "invalid log format {}, expected text, json or journald",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct HttpConfig {
+    /// timeouts in seconds
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+    timeout: Option<u64>,
+    /// http, https or socks5 proxy url
+    pub proxy: Option<String>,
+    /// additional root certificates in pem format
+    pub ca_certificates: Option<Vec<PathBuf>>,
+    /// pem encoded client certificate and key for mutual tls
+    pub client_certificate: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    max_redirects: Option<usize>,
+}
+
+impl HttpConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout.unwrap_or(10))
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout.map(Duration::from_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(60))
+    }
+
+    pub fn max_redirects(&self) -> usize {
+        self.max_redirects.unwrap_or(10)
+    }
+
+    /// Combine with more specific settings, settings set in `overrides` take precedence
+    fn merge(&self, overrides: &HttpConfig) -> HttpConfig {
+        HttpConfig {
+            connect_timeout: overrides.connect_timeout.or(self.connect_timeout),
+            read_timeout: overrides.read_timeout.or(self.read_timeout),
+            timeout: overrides.timeout.or(self.timeout),
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+            ca_certificates: overrides
+                .ca_certificates
+                .clone()
+                .or_else(|| self.ca_certificates.clone()),
+            client_certificate: overrides
+                .client_certificate
+                .clone()
+                .or_else(|| self.client_certificate.clone()),
+            client_key: overrides
+                .client_key
+                .clone()
+                .or_else(|| self.client_key.clone()),
+            max_redirects: overrides.max_redirects.or(self.max_redirects),
+        }
+    }
+}
+
+/// The mqtt broker used by all mqtt actions
 #[derive(Debug, Deserialize)]
+pub struct MqttConfig {
+    pub host: String,
+    port: Option<u16>,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    #[serde(default)]
+    pub tls: bool,
+}
+
+impl MqttConfig {
+    pub fn port(&self) -> u16 {
+        match (self.port, self.tls) {
+            (Some(port), _) => port,
+            (None, true) => 8883,
+            (None, false) => 1883,
+        }
+    }
+}
+
+fn default_mqtt_client_id() -> String {
+    "rss-webhook-trigger".into()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawFeedConfig")]
 pub struct FeedConfig {
     pub feed: String,
+    /// ignore the ttl, skipHours, skipDays and update period set by the feed
+    pub ignore_hints: bool,
+    /// only fetch the feed at the times matching the cron expression
+    pub cron: Option<Cron>,
+    /// credentials and headers send when fetching the feed
+    pub auth: FeedAuth,
+    /// http settings for fetching the feed, merged with the global settings
+    pub http: HttpConfig,
+    /// what to do with the items found when first fetching the feed
+    pub on_start: OnStart,
+    /// also trigger when existing items are edited
+    pub detect_edits: bool,
+    /// fields identifying an item, instead of the default guid, pubDate or link for rss and id for atom
+    pub item_key: Option<ItemKeyConfig>,
+    /// number of consecutive parse failures that are logged as info instead of a warning
+    ///
+    /// This only changes the log level, the feed is marked as broken on the first failure.
+    pub tolerate_parse_failures: u32,
+    /// only consider items with an enclosure or media file of one of these mime types
+    pub enclosure_types: Vec<String>,
+    pub actions: Vec<ActionConfig>,
+}
+
+/// Feed config as written in the config file, the raw webhook options are kept at the top level for compatibility
+#[derive(Deserialize)]
+struct RawFeedConfig {
+    feed: String,
+    hook: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, HeaderVal>,
+    #[serde(default)]
+    body: Value,
+    #[serde(default)]
+    template: bool,
+    signature: Option<SignatureConfig>,
+    quiet: Option<QuietWindow>,
+    debounce: Option<u64>,
+    #[serde(default)]
+    items: ItemSelection,
+    #[serde(default = "default_trigger_on")]
+    trigger_on: Vec<ChangeKind>,
+    #[serde(default)]
+    ignore_hints: bool,
+    #[serde(default, deserialize_with = "deserialize_cron")]
+    cron: Option<Cron>,
+    #[serde(flatten)]
+    auth: FeedAuth,
+    #[serde(default)]
+    http: HttpConfig,
+    #[serde(default)]
+    on_start: OnStart,
+    #[serde(default)]
+    detect_edits: bool,
+    item_key: Option<ItemKeyConfig>,
+    #[serde(default = "default_tolerate_parse_failures")]
+    tolerate_parse_failures: u32,
+    #[serde(default)]
+    enclosure_types: Vec<String>,
+    #[serde(default)]
+    action: Vec<ActionConfig>,
+}
+
+impl From<RawFeedConfig> for FeedConfig {
+    fn from(raw: RawFeedConfig) -> Self {
+        let mut actions = Vec::with_capacity(raw.action.len() + 1);
+        if let Some(hook) = raw.hook {
+            actions.push(ActionConfig {
+                kind: ActionKind::Webhook(WebhookConfig {
+                    hook,
+                    headers: raw.headers,
+                    body: raw.body,
+                    template: raw.template,
+                    signature: raw.signature,
+                }),
+                quiet: raw.quiet,
+                debounce: raw.debounce,
+                items: raw.items,
+                trigger_on: raw.trigger_on,
+            });
+        }
+        actions.extend(raw.action);
+        FeedConfig {
+            feed: raw.feed,
+            ignore_hints: raw.ignore_hints,
+            cron: raw.cron,
+            auth: raw.auth,
+            http: raw.http,
+            on_start: raw.on_start,
+            detect_edits: raw.detect_edits,
+            item_key: raw.item_key,
+            tolerate_parse_failures: raw.tolerate_parse_failures,
+            enclosure_types: raw.enclosure_types,
+            actions,
+        }
+    }
+}
+
+/// How to handle the items in a feed when it is fetched for the first time
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnStart {
+    /// only remember the current items
+    #[default]
+    Skip,
+    /// trigger the actions with the newest item
+    Trigger,
+    /// trigger the actions with all items published after the given time
+    TriggerIfNewerThan(#[serde(with = "time::serde::rfc3339")] OffsetDateTime),
+}
+
+impl OnStart {
+    /// Get the items to trigger for, from the items found on the first fetch, newest first
+    pub fn select(self, items: Vec<FeedItem>) -> Option<Vec<FeedItem>> {
+        let items: Vec<FeedItem> = match self {
+            OnStart::Skip => return None,
+            OnStart::Trigger => items.into_iter().take(1).collect(),
+            OnStart::TriggerIfNewerThan(since) => items
+                .into_iter()
+                .filter(|item| item.published.is_some_and(|published| published > since))
+                .collect(),
+        };
+        (!items.is_empty()).then_some(items)
+    }
+}
+
+fn default_tolerate_parse_failures() -> u32 {
+    2
+}
+
+/// Which fields of an item form its identity and how they are normalized
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemKeyConfig {
+    #[serde(deserialize_with = "deserialize_key_fields")]
+    pub fields: Vec<KeyField>,
+    /// combine all fields or only use the first field that is set
+    #[serde(default)]
+    pub combine: KeyCombine,
+    #[serde(default)]
+    pub normalize: Vec<Normalize>,
+}
+
+fn deserialize_key_fields<'de, D>(deserializer: D) -> Result<Vec<KeyField>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fields = Vec::<KeyField>::deserialize(deserializer)?;
+    if fields.is_empty() {
+        return Err(D::Error::custom("item_key needs at least one field"));
+    }
+    Ok(fields)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyField {
+    /// rss guid or atom id
+    #[serde(alias = "id")]
+    Guid,
+    Title,
+    Link,
+    /// rss pubDate or atom published date
+    #[serde(alias = "published")]
+    PubDate,
+    /// url of the first enclosure
+    Enclosure,
+}
+
+impl KeyField {
+    fn name(self) -> &'static str {
+        match self {
+            KeyField::Guid => "guid",
+            KeyField::Title => "title",
+            KeyField::Link => "link",
+            KeyField::PubDate => "pub_date",
+            KeyField::Enclosure => "enclosure",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyCombine {
+    #[default]
+    All,
+    First,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalize {
+    /// remove leading and trailing whitespace
+    Trim,
+    Lowercase,
+    /// remove the query string from urls, values that aren't absolute urls are left as is
+    StripQuery,
+    /// remove the fragment from urls, values that aren't absolute urls are left as is
+    StripFragment,
+}
+
+impl ItemKeyConfig {
+    /// Build the key for an item, returns `None` if none of the fields are set
+    pub fn key<'a>(&self, kind: &str, value: impl Fn(KeyField) -> Option<&'a str>) -> Option<Key> {
+        let mut key = Key::builder(kind);
+        let mut found = false;
+        for &field in &self.fields {
+            if let Some(value) = value(field) {
+                key = key.field(field.name()).field(self.normalize(value));
+                found = true;
+                if self.combine == KeyCombine::First {
+                    break;
+                }
+            }
+        }
+        found.then(|| key.finish())
+    }
+
+    fn normalize(&self, value: &str) -> String {
+        let mut value = value.to_string();
+        for rule in &self.normalize {
+            match rule {
+                Normalize::Trim => value = value.trim().to_string(),
+                Normalize::Lowercase => value = value.to_lowercase(),
+                Normalize::StripQuery | Normalize::StripFragment => match Url::parse(&value) {
+                    Ok(mut url) => {
+                        if *rule == Normalize::StripQuery {
+                            url.set_query(None);
+                        } else {
+                            url.set_fragment(None);
+                        }
+                        value = url.into();
+                    }
+                    Err(_) => debug!(value, ?rule, "not normalizing key field that isn't a url"),
+                },
+            }
+        }
+        value
+    }
+}
+
+/// Credentials and extra headers for fetching private feeds
+#[derive(Debug, Default, Deserialize)]
+pub struct FeedAuth {
+    #[serde(default, rename = "feed_headers")]
+    headers: HashMap<String, HeaderVal>,
+    basic_auth: Option<BasicAuth>,
+    bearer_token: Option<Secret>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: Option<Secret>,
+}
+
+impl FeedAuth {
+    /// Add the configured headers and credentials to the request, all values are marked as sensitive
+    pub fn apply(&self, mut req: RequestBuilder) -> RequestBuilder {
+        for (key, value) in &self.headers {
+            req = req.header(key, value);
+        }
+        if let Some(auth) = &self.basic_auth {
+            req = req.basic_auth(&auth.username, auth.password.as_ref().map(Secret::expose));
+        }
+        if let Some(token) = &self.bearer_token {
+            req = req.bearer_auth(token.expose());
+        }
+        req
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionConfig {
+    #[serde(flatten)]
+    pub kind: ActionKind,
+    /// don't trigger the action during this window, triggers are delivered once the window ends
+    pub quiet: Option<QuietWindow>,
+    /// collect all changes within this many seconds after the first change into a single trigger
+    pub debounce: Option<u64>,
+    /// which of the new items to pass to the action
+    #[serde(default)]
+    pub items: ItemSelection,
+    /// the kinds of changes that trigger the action
+    #[serde(default = "default_trigger_on")]
+    pub trigger_on: Vec<ChangeKind>,
+}
+
+fn default_trigger_on() -> Vec<ChangeKind> {
+    vec![ChangeKind::Added]
+}
+
+impl ActionConfig {
+    pub fn kind(&self) -> &'static str {
+        self.kind.name()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemSelection {
+    /// only the oldest of the new items
+    First,
+    /// only the newest item
+    Last,
+    #[default]
+    All,
+}
+
+impl ItemSelection {
+    /// Select from the new items, which are ordered newest first
+    pub fn select(self, items: &[FeedItem]) -> &[FeedItem] {
+        match self {
+            ItemSelection::First => &items[items.len().saturating_sub(1)..],
+            ItemSelection::Last => &items[..items.len().min(1)],
+            ItemSelection::All => items,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionKind {
+    Webhook(WebhookConfig),
+    Discord(DiscordConfig),
+    Slack(SlackConfig),
+    Matrix(MatrixConfig),
+    Ntfy(NtfyConfig),
+    Gotify(GotifyConfig),
+    Exec(ExecConfig),
+    Smtp(SmtpConfig),
+    Mqtt(MqttActionConfig),
+    Github(GithubConfig),
+    Gitlab(GitlabConfig),
+    Woodpecker(WoodpeckerConfig),
+    Forgejo(ForgejoConfig),
+    Custom(CustomActionConfig),
+}
+
+impl ActionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActionKind::Webhook(_) => "webhook",
+            ActionKind::Discord(_) => "discord",
+            ActionKind::Slack(_) => "slack",
+            ActionKind::Matrix(_) => "matrix",
+            ActionKind::Ntfy(_) => "ntfy",
+            ActionKind::Gotify(_) => "gotify",
+            ActionKind::Exec(_) => "exec",
+            ActionKind::Smtp(_) => "smtp",
+            ActionKind::Mqtt(_) => "mqtt",
+            ActionKind::Github(_) => "github",
+            ActionKind::Gitlab(_) => "gitlab",
+            ActionKind::Woodpecker(_) => "woodpecker",
+            ActionKind::Forgejo(_) => "forgejo",
+            ActionKind::Custom(_) => "custom",
+        }
+    }
+
+    /// Short description of where the action is send to, for logging
+    ///
+    /// Targets containing secrets, like chat webhook urls, only show the action type.
+    pub fn target(&self) -> String {
+        match self {
+            ActionKind::Webhook(hook) => {
+                loggable_url(&hook.hook).unwrap_or_else(|| self.name().into())
+            }
+            ActionKind::Matrix(matrix) => matrix.room.clone(),
+            ActionKind::Ntfy(ntfy) => ntfy.topic.clone(),
+            ActionKind::Gotify(gotify) => {
+                loggable_url(&gotify.server).unwrap_or_else(|| self.name().into())
+            }
+            ActionKind::Exec(exec) => exec.command.clone(),
+            ActionKind::Smtp(smtp) => smtp.to.join(", "),
+            ActionKind::Mqtt(mqtt) => mqtt.topic.clone(),
+            ActionKind::Github(github) => github.repository.clone(),
+            ActionKind::Gitlab(gitlab) => gitlab.project.clone(),
+            ActionKind::Woodpecker(woodpecker) => woodpecker.repo_id.to_string(),
+            ActionKind::Forgejo(forgejo) => forgejo.repository.clone(),
+            ActionKind::Custom(custom) => custom.name.clone(),
+            ActionKind::Discord(_) | ActionKind::Slack(_) => self.name().into(),
+        }
+    }
+}
+
+/// Url without credentials, query or fragment, which often contain tokens
+fn loggable_url(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    url.set_username("").ok()?;
+    url.set_password(None).ok()?;
+    url.set_query(None);
+    url.set_fragment(None);
+    Some(url.into())
+}
+
+/// An action registered in the `ActionRegistry` by a program using the library
+#[derive(Debug, Deserialize)]
+pub struct CustomActionConfig {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookConfig {
     pub hook: String,
     #[serde(default)]
     pub headers: HashMap<String, HeaderVal>,
     #[serde(default)]
     pub body: Value,
+    /// fill in placeholders in the strings of the body, off by default so literal braces are sent as is
+    #[serde(default)]
+    pub template: bool,
+    pub signature: Option<SignatureConfig>,
+}
+
+/// Discord incoming webhook
+#[derive(Debug, Deserialize)]
+pub struct DiscordConfig {
+    pub webhook: Secret,
+    pub username: Option<String>,
+}
+
+/// Slack incoming webhook
+#[derive(Debug, Deserialize)]
+pub struct SlackConfig {
+    pub webhook: Secret,
+}
+
+/// Matrix room message using the client-server api
+#[derive(Debug, Deserialize)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub room: String,
+    pub access_token: Secret,
+    /// send the message as `m.notice` instead of `m.text`
+    #[serde(default)]
+    pub notice: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+    pub topic: String,
+    pub token: Option<Secret>,
+    pub priority: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".into()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GotifyConfig {
+    pub server: String,
+    pub token: Secret,
+    pub priority: Option<u8>,
+}
+
+/// Run a local command, the item is passed as environment variables and as json on stdin
+#[derive(Debug, Deserialize)]
+pub struct ExecConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub working_directory: Option<PathBuf>,
+    /// timeout in seconds
+    #[serde(default = "default_exec_timeout")]
+    pub timeout: u64,
+}
+
+impl ExecConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+fn default_exec_timeout() -> u64 {
+    5 * 60
+}
+
+/// Send an email for new items
+#[derive(Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_mail_subject")]
+    pub subject: String,
+    #[serde(default = "default_mail_body")]
+    pub body: String,
+    /// send one mail for all new items instead of one mail per item
+    #[serde(default)]
+    pub batch: bool,
+    #[serde(default = "default_mail_batch_subject")]
+    pub batch_subject: String,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// implicit tls, usually on port 465
+    Tls,
+    /// upgrade the connection using STARTTLS, usually on port 587
+    #[default]
+    Starttls,
+    /// unencrypted connection, only use this for a local relay
+    None,
+}
+
+fn default_mail_subject() -> String {
+    "{title}".into()
+}
+
+fn default_mail_body() -> String {
+    "{title}\n{link}\n\n{summary}".into()
+}
+
+fn default_mail_batch_subject() -> String {
+    "{count} new items in {feed}".into()
+}
+
+/// Publish a message to the configured mqtt broker
+#[derive(Debug, Deserialize)]
+pub struct MqttActionConfig {
+    pub topic: String,
+    #[serde(default = "default_qos", deserialize_with = "deserialize_qos")]
+    pub qos: QoS,
+    #[serde(default)]
+    pub retain: bool,
+}
+
+fn default_qos() -> QoS {
+    QoS::AtMostOnce
+}
+
+/// Trigger a GitHub `repository_dispatch` event, or a `workflow_dispatch` if a workflow is set
+#[derive(Debug, Deserialize)]
+pub struct GithubConfig {
+    #[serde(default = "default_github_api")]
+    pub api: String,
+    /// `owner/repo`
+    pub repository: String,
+    pub token: Secret,
+    pub workflow: Option<String>,
+    #[serde(rename = "ref", default = "default_ref")]
+    pub git_ref: String,
+    #[serde(default = "default_event_type")]
+    pub event_type: String,
+    /// templated workflow inputs or extra client payload fields
+    #[serde(default)]
+    pub inputs: HashMap<String, String>,
+}
+
+fn default_github_api() -> String {
+    "https://api.github.com".into()
+}
+
+fn default_event_type() -> String {
+    "feed-update".into()
+}
+
+fn default_ref() -> String {
+    "main".into()
+}
+
+/// Trigger a GitLab pipeline using a pipeline trigger token
+#[derive(Debug, Deserialize)]
+pub struct GitlabConfig {
+    #[serde(default = "default_gitlab_server")]
+    pub server: String,
+    /// project id or `namespace/project` path
+    pub project: String,
+    pub token: Secret,
+    #[serde(rename = "ref", default = "default_ref")]
+    pub git_ref: String,
+    /// templated pipeline variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+fn default_gitlab_server() -> String {
+    "https://gitlab.com".into()
+}
+
+/// Start a Woodpecker CI pipeline
+#[derive(Debug, Deserialize)]
+pub struct WoodpeckerConfig {
+    pub server: String,
+    /// numeric id of the repository
+    pub repo_id: u64,
+    pub token: Secret,
+    #[serde(default = "default_ref")]
+    pub branch: String,
+    /// templated pipeline variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// Trigger a Forgejo (or Gitea) Actions `workflow_dispatch`
+#[derive(Debug, Deserialize)]
+pub struct ForgejoConfig {
+    pub server: String,
+    /// `owner/repo`
+    pub repository: String,
+    pub workflow: String,
+    pub token: Secret,
+    #[serde(rename = "ref", default = "default_ref")]
+    pub git_ref: String,
+    /// templated workflow inputs
+    #[serde(default)]
+    pub inputs: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignatureConfig {
+    pub secret: Secret,
+    #[serde(default = "default_signature_header")]
+    pub header: String,
+    /// include a timestamp in the signed payload to prevent replays
+    timestamp: Option<bool>,
+    #[serde(default = "default_timestamp_header")]
+    pub timestamp_header: String,
+}
+
+const GITHUB_SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+impl SignatureConfig {
+    /// Whether to sign a timestamp, by default only when not using the github header
+    ///
+    /// Github style verifiers expect the signature to cover only the body.
+    pub fn timestamp(&self) -> bool {
+        self.timestamp
+            .unwrap_or_else(|| !self.header.eq_ignore_ascii_case(GITHUB_SIGNATURE_HEADER))
+    }
+}
+
+fn default_signature_header() -> String {
+    GITHUB_SIGNATURE_HEADER.into()
+}
+
+fn default_timestamp_header() -> String {
+    "X-Hub-Signature-Timestamp".into()
 }
 
 impl Config {
@@ -33,20 +854,50 @@ impl Config {
             error,
             path: path.into(),
         })?;
-        toml::from_str(&file).map_err(|error| ConfigError::Parse {
+        Config::from_toml(&file).map_err(|error| ConfigError::Parse {
             error,
             path: path.into(),
         })
     }
 
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        let mut config: Config = toml::from_str(content)?;
+        for feed in &mut config.feed {
+            feed.http = config.http.merge(&feed.http);
+            for action in &feed.actions {
+                if let ActionKind::Smtp(smtp) = &action.kind {
+                    if smtp.password.is_some() && smtp.username.is_none() {
+                        return Err(toml::de::Error::custom(
+                            "smtp password is configured without a username",
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
     pub fn interval(&self) -> Duration {
         Duration::from_secs(self.interval.unwrap_or(30 * 60))
     }
+
+    pub fn schedule(&self) -> FetchSchedule {
+        FetchSchedule {
+            interval: self.interval(),
+            min_interval: Duration::from_secs(self.min_interval.unwrap_or(60)),
+            max_interval: Duration::from_secs(self.max_interval.unwrap_or(24 * 60 * 60)),
+        }
+    }
 }
 
-#[derive(Debug)]
 pub struct HeaderVal(String);
 
+impl Debug for HeaderVal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("HeaderVal(<redacted>)")
+    }
+}
+
 impl<'de> Deserialize<'de> for HeaderVal {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -62,14 +913,49 @@ impl TryFrom<&HeaderVal> for HeaderValue {
     type Error = InvalidHeaderValue;
 
     fn try_from(header: &HeaderVal) -> Result<Self, Self::Error> {
-        header.0.as_str().try_into()
+        let mut value: HeaderValue = header.0.as_str().try_into()?;
+        // header values can be loaded from secret files, keep them out of the logs
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        let str = load_secret(raw).map_err(D::Error::custom)?;
+        Ok(Secret::new(str))
     }
 }
 
+fn deserialize_cron<'de, D>(deserializer: D) -> Result<Option<Cron>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    Cron::new(&raw)
+        .parse()
+        .map(Some)
+        .map_err(|e| D::Error::custom(format!("invalid cron expression {}: {}", raw, e)))
+}
+
+fn deserialize_qos<'de, D>(deserializer: D) -> Result<QoS, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = u8::deserialize(deserializer)?;
+    qos(raw).map_err(|_| D::Error::custom(format!("invalid mqtt qos {}, expected 0, 1 or 2", raw)))
+}
+
 fn load_secret(raw: String) -> Result<String, SecretError> {
     let path: &Path = raw.as_ref();
     if (raw.starts_with('/') && path.exists()) || raw.contains("$CREDENTIALS_DIRECTORY") {
-        load(&raw)
+        let value = load(&raw)?;
+        secret::register(&value);
+        Ok(value)
     } else {
         Ok(raw)
     }
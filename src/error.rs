@@ -1,14 +1,15 @@
 use reqwest::StatusCode;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ParseFeedError {
     #[error("{0}")]
     Parse(<syndication::Feed as FromStr>::Err),
-    #[error("Empty feed")]
-    Empty,
     #[error("No guid, pubDate or link set on feed item")]
     MissingKey,
 }
@@ -39,6 +40,26 @@ pub enum ConfigError {
     },
 }
 
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Error while reading {}: {:#}", path.display(), error)]
+    Read {
+        error: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("Invalid certificate {}: {:#}", path.display(), error)]
+    Certificate {
+        error: reqwest::Error,
+        path: PathBuf,
+    },
+    #[error("Invalid proxy: {0:#}")]
+    Proxy(reqwest::Error),
+    #[error("Both a client certificate and client key need to be configured")]
+    IncompleteIdentity,
+    #[error("Error while creating http client: {0:#}")]
+    Build(reqwest::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum HubError {
     #[error("Error while fetching docker hub info: {0:#}")]
@@ -59,4 +80,161 @@ pub enum FetchError {
     Feed(#[from] FetchFeedError),
     #[error(transparent)]
     Hub(#[from] HubError),
+    #[error("No source registered for {0}")]
+    UnknownSource(String),
+}
+
+impl FetchError {
+    /// Stable identifier for the type of error, used in structured logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::Feed(FetchFeedError::Network(_))
+            | FetchError::Hub(HubError::Network(_)) => "network",
+            FetchError::Feed(FetchFeedError::Parse(ParseFeedError::Parse(_)))
+            | FetchError::Hub(HubError::Parse(_)) => "parse",
+            FetchError::Feed(FetchFeedError::Parse(ParseFeedError::MissingKey)) => "missing_key",
+            FetchError::Feed(FetchFeedError::ServerError(_))
+            | FetchError::Hub(HubError::ServerError(_)) => "server_error",
+            FetchError::Feed(FetchFeedError::ClientError(_))
+            | FetchError::Hub(HubError::ClientError(_)) => "client_error",
+            FetchError::Hub(HubError::InvalidFormat) | FetchError::UnknownSource(_) => {
+                "invalid_source"
+            }
+        }
+    }
+
+    /// Whether the feed was fetched, but couldn't be parsed
+    pub fn is_parse_error(&self) -> bool {
+        matches!(
+            self,
+            FetchError::Feed(FetchFeedError::Parse(_)) | FetchError::Hub(HubError::Parse(_))
+        )
+    }
+
+    /// The http status returned by the server, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            FetchError::Feed(FetchFeedError::Network(e))
+            | FetchError::Hub(HubError::Network(e)) => e.status(),
+            FetchError::Feed(FetchFeedError::ServerError(status))
+            | FetchError::Feed(FetchFeedError::ClientError(status))
+            | FetchError::Hub(HubError::ServerError(status))
+            | FetchError::Hub(HubError::ClientError(status)) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ActionError {
+    #[error("Error while sending request: {0:#}")]
+    Network(#[from] reqwest::Error),
+    #[error("Invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("Failed to run command: {0:#}")]
+    Spawn(std::io::Error),
+    #[error("Command timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("Command failed with {0}")]
+    ExitStatus(ExitStatus),
+    #[error("Invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("Error while building email: {0}")]
+    Mail(#[from] lettre::error::Error),
+    #[error("Error while sending email: {0:#}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Error while publishing mqtt message: {0}")]
+    Mqtt(#[from] rumqttc::ClientError),
+    #[error("Not connected to the mqtt broker")]
+    MqttDisconnected,
+    #[error("Too many mqtt messages waiting for the broker")]
+    MqttQueueFull,
+    #[error("No mqtt broker configured")]
+    MqttNotConfigured,
+    #[error("Invalid repository {0}, expected owner/repo")]
+    InvalidRepository(String),
+    #[error("No custom action registered as {0}")]
+    UnknownAction(String),
+    #[error("No items to trigger the action for")]
+    NoItems,
+}
+
+impl ActionError {
+    /// Stable identifier for the type of error, used in structured logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ActionError::Network(e) if e.is_timeout() => "timeout",
+            ActionError::Network(e) if e.is_status() => "http_status",
+            ActionError::Network(_) => "network",
+            ActionError::Url(_) => "invalid_url",
+            ActionError::Spawn(_) => "spawn",
+            ActionError::Timeout(_) => "timeout",
+            ActionError::ExitStatus(_) => "exit_status",
+            ActionError::Address(_) => "invalid_address",
+            ActionError::Mail(_) => "mail",
+            ActionError::Smtp(_) => "smtp",
+            ActionError::Mqtt(_) => "mqtt",
+            ActionError::MqttDisconnected => "mqtt_disconnected",
+            ActionError::MqttQueueFull => "mqtt_queue_full",
+            ActionError::MqttNotConfigured => "mqtt_not_configured",
+            ActionError::InvalidRepository(_) => "invalid_repository",
+            ActionError::UnknownAction(_) => "unknown_action",
+            ActionError::NoItems => "no_items",
+        }
+    }
+
+    /// The http status returned by the server, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ActionError::Network(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// The url of the failed request, if any
+    pub fn url(&self) -> Option<&reqwest::Url> {
+        match self {
+            ActionError::Network(e) => e.url(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("Invalid log filter: {0}")]
+    Filter(#[from] tracing_subscriber::filter::ParseError),
+    #[error("Failed to connect to journald: {0:#}")]
+    Journald(std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("Error while accessing history file {}: {:#}", path.display(), error)]
+    Io {
+        error: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("Error while serializing history entry: {0:#}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("No history file configured")]
+    NotConfigured,
+}
+
+impl HistoryError {
+    pub fn io(error: std::io::Error, path: &Path) -> Self {
+        HistoryError::Io {
+            error,
+            path: path.into(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StatusError {
+    #[error("Failed to listen on {address}: {error:#}")]
+    Bind {
+        error: std::io::Error,
+        address: SocketAddr,
+    },
 }
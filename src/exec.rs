@@ -0,0 +1,70 @@
+use crate::config::ExecConfig;
+use crate::error::ActionError;
+use crate::item::FeedItem;
+use crate::secret::Redacted;
+use serde_json::json;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+pub async fn run(config: &ExecConfig, feed: &str, items: &[FeedItem]) -> Result<(), ActionError> {
+    // the environment variables describe the newest item, all new items are passed on stdin
+    let item = items.first().cloned().unwrap_or_default();
+    let mut command = Command::new(&config.command);
+    command
+        .args(&config.args)
+        .envs(&config.env)
+        .env("RSS_FEED", feed)
+        .env("RSS_ITEM_TITLE", item.title.as_deref().unwrap_or_default())
+        .env("RSS_ITEM_LINK", item.link.as_deref().unwrap_or_default())
+        .env(
+            "RSS_ITEM_SUMMARY",
+            item.summary.as_deref().unwrap_or_default(),
+        )
+        .env(
+            "RSS_ITEM_ENCLOSURE",
+            item.enclosure_url().unwrap_or_default(),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = &config.working_directory {
+        command.current_dir(dir);
+    }
+
+    // the environment may hold credentials, so only the program is logged
+    debug!(command = config.command, "running command");
+    let mut child = command.spawn().map_err(ActionError::Spawn)?;
+
+    let input = serde_json::to_vec(&json!({ "feed": feed, "item": item, "items": items }))
+        .expect("json values can always be serialized");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // the command is free to ignore stdin, so a broken pipe isn't an error
+    tokio::spawn(async move {
+        if let Err(e) = stdin.write_all(&input).await {
+            debug!(error = %e, "failed to write item to command stdin");
+        }
+    });
+
+    // the child is killed when the future is dropped on timeout
+    let output = timeout(config.timeout(), child.wait_with_output())
+        .await
+        .map_err(|_| ActionError::Timeout(config.timeout()))?
+        .map_err(ActionError::Spawn)?;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!(command = config.command, "{}", Redacted(line));
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        warn!(command = config.command, "{}", Redacted(line));
+    }
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ActionError::ExitStatus(output.status))
+    }
+}
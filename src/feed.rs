@@ -0,0 +1,342 @@
+use crate::change::{ChangeEvent, ChangeKind, TagDiff};
+use crate::client::ClientPool;
+use crate::clock::Clock;
+use crate::config::FeedConfig;
+use crate::error::FetchError;
+use crate::fetcher::{
+    next_fetch, CacheHeaders, FetchPlan, FetchPlanInput, FetchResponse, FetchSchedule,
+};
+use crate::key::Key;
+use crate::secret::Redacted;
+use crate::source::SourceRegistry;
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{debug, info, instrument, warn};
+
+struct CachedFeed {
+    key: Key,
+    /// revision of every item by key
+    items: HashMap<Key, Key>,
+    tags: Option<HashMap<String, Key>>,
+    /// the last fetch failed or the feed was empty
+    broken: bool,
+}
+
+/// Keeps track of the state of all feeds, to detect new items and plan the next fetch
+pub struct FeedFetcher {
+    clients: ClientPool,
+    sources: SourceRegistry,
+    schedule: FetchSchedule,
+    cache: HashMap<String, CachedFeed>,
+    fetch_plans: HashMap<String, FetchPlan>,
+    /// number of consecutive parse failures by feed
+    parse_failures: HashMap<String, u32>,
+    clock: Clock,
+}
+
+impl FeedFetcher {
+    pub fn new(schedule: FetchSchedule, clients: ClientPool, sources: SourceRegistry) -> Self {
+        FeedFetcher {
+            clients,
+            sources,
+            schedule,
+            cache: HashMap::default(),
+            fetch_plans: HashMap::default(),
+            parse_failures: HashMap::default(),
+            clock: Clock::default(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The earliest time any feed should be fetched again
+    pub fn next_planned_fetch(&self) -> Option<Instant> {
+        self.fetch_plans.values().map(|plan| plan.time).min()
+    }
+
+    pub fn should_update(&self, feed: &str) -> bool {
+        match self.fetch_plans.get(feed) {
+            Some(plan) => plan.is_elapsed(self.clock.now()),
+            None => true,
+        }
+    }
+
+    /// Check the feed for updates, returning the changes if the feed changed
+    ///
+    /// New items and, if enabled for the feed, edited items are returned as separate changes.
+    /// Every fetch is logged as a single "feed fetched" event.
+    #[instrument(skip_all, fields(feed = config.feed))]
+    pub async fn check_feed_updated(&mut self, config: &FeedConfig) -> Vec<ChangeEvent> {
+        let feed = config.feed.as_str();
+        let schedule = match &config.cron {
+            Some(_) => self.schedule.for_cron(),
+            None => self.schedule,
+        };
+        if let (Some(cron), false) = (&config.cron, self.fetch_plans.contains_key(feed)) {
+            // wait for the first matching time instead of fetching right away
+            let mut plan = FetchPlan::default();
+            plan.apply_cron(cron, &self.clock);
+            self.fetch_plans.insert(feed.into(), plan);
+        }
+        if !self.should_update(feed) {
+            debug!("skipping feed until the next planned fetch");
+            return Vec::new();
+        }
+        let mut plan = self.fetch_plans.remove(feed).unwrap_or_default();
+        let previous_hints = std::mem::take(&mut plan.hints);
+
+        let started = Instant::now();
+        let client = self.clients.get(&config.http);
+        let source = self.sources.get(feed);
+        let fetch_result = match source {
+            Some(source) => source.fetch(client, config, &plan.headers).await,
+            None => FetchResponse::Error {
+                error: FetchError::UnknownSource(feed.into()),
+                headers: CacheHeaders::default(),
+            },
+        };
+        let (result, new_plan) = fetch_result.into_result();
+        let is_retry = new_plan.is_retry();
+        let is_failed = matches!(new_plan, FetchPlanInput::Failed);
+        let mut next_plan = next_fetch(&schedule, plan, Some(new_plan), self.clock.now());
+        next_plan.hints = match &result {
+            Ok(Some(snapshot)) => snapshot.hints.clone(),
+            _ => previous_hints,
+        };
+        if !is_retry && !is_failed && !config.ignore_hints {
+            next_plan.apply_hints(&schedule, &self.clock);
+        }
+        if let Some(cron) = &config.cron {
+            next_plan.apply_cron(cron, &self.clock);
+        }
+        let failures = next_plan.failures;
+        let backoff = next_plan.time.saturating_duration_since(self.clock.now());
+        self.fetch_plans.insert(feed.into(), next_plan);
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let source = source.map_or("unknown", |source| source.name());
+
+        let parse_failures = match &result {
+            Err(e) if e.is_parse_error() => {
+                let count = self.parse_failures.entry(feed.into()).or_default();
+                *count += 1;
+                *count
+            }
+            // other errors in between don't reset the count, only a feed that parses again does
+            Ok(Some(_)) => {
+                self.parse_failures.remove(feed);
+                0
+            }
+            _ => 0,
+        };
+        let snapshot = match result {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                // tolerated failures still mark the feed as broken, like any other failure
+                if let Some(cached) = self.cache.get_mut(feed) {
+                    cached.broken = true;
+                }
+                let event = FetchEvent {
+                    feed,
+                    source,
+                    status: "failed",
+                    duration_ms,
+                    http_status: e.status().map_or(0, |status| status.as_u16()),
+                    error_kind: e.kind(),
+                    error: Redacted(&e).to_string(),
+                    failures,
+                    backoff_secs: backoff.as_secs(),
+                    parse_failures,
+                    ..FetchEvent::default()
+                };
+                // feeds sometimes briefly serve an error page, only warn if it persists
+                let tolerated =
+                    parse_failures > 0 && parse_failures <= config.tolerate_parse_failures;
+                event.log(!tolerated);
+                return Vec::new();
+            }
+        };
+
+        let change = |kind, old_key, new_key, items, tags| ChangeEvent {
+            kind,
+            source,
+            feed: feed.into(),
+            items,
+            old_key,
+            new_key,
+            tags,
+        };
+        let (status, changes) = match (self.cache.get_mut(feed), snapshot) {
+            (Some(cached), Some(snapshot)) => {
+                debug!(
+                    cached = %cached.key,
+                    new_key = %snapshot.key,
+                    "checked existing feed"
+                );
+                let recovering = std::mem::replace(&mut cached.broken, snapshot.items.is_empty());
+                let old_key = std::mem::replace(&mut cached.key, snapshot.key);
+                if snapshot.items.is_empty() {
+                    // keep the seen items, so they don't trigger again once the feed has items again
+                    let status = if old_key == snapshot.key {
+                        "unchanged"
+                    } else {
+                        "empty"
+                    };
+                    (status, Vec::new())
+                } else {
+                    let tags = std::mem::replace(&mut cached.tags, snapshot.tags.clone());
+                    let seen = std::mem::replace(&mut cached.items, snapshot.item_revisions());
+                    let key = snapshot.key;
+                    let edited = if config.detect_edits {
+                        snapshot.edited_items(&seen)
+                    } else {
+                        Vec::new()
+                    };
+                    let mut changes = Vec::new();
+                    if key != old_key {
+                        let diff = match (&tags, &cached.tags) {
+                            (Some(old), Some(new)) => Some(TagDiff::between(old, new)),
+                            _ => None,
+                        };
+                        // after a failure only trigger for items that weren't seen before
+                        let items = if recovering {
+                            snapshot.new_items(&seen)
+                        } else {
+                            snapshot.into_new_items(&seen)
+                        };
+                        if !items.is_empty() {
+                            changes.push(change(
+                                ChangeKind::Added,
+                                Some(old_key),
+                                key,
+                                items,
+                                diff,
+                            ));
+                        }
+                    }
+                    if !edited.is_empty() {
+                        changes.push(change(
+                            ChangeKind::Updated,
+                            Some(old_key),
+                            key,
+                            edited,
+                            None,
+                        ));
+                    }
+                    let status = match changes.first().map(|change| change.kind) {
+                        Some(ChangeKind::Added) => "updated",
+                        Some(ChangeKind::Updated) => "edited",
+                        None if recovering => "recovered",
+                        None => "unchanged",
+                    };
+                    (status, changes)
+                }
+            }
+            (None, Some(mut snapshot)) => {
+                let key = snapshot.key;
+                self.cache.insert(
+                    feed.into(),
+                    CachedFeed {
+                        key,
+                        items: snapshot.item_revisions(),
+                        tags: snapshot.tags.take(),
+                        broken: snapshot.items.is_empty(),
+                    },
+                );
+                let changes = config
+                    .on_start
+                    .select(snapshot.items)
+                    .map(|items| change(ChangeKind::Added, None, key, items, None))
+                    .into_iter()
+                    .collect();
+                ("new", changes)
+            }
+            (_, None) if is_retry => ("rate_limited", Vec::new()),
+            (_, None) => ("not_modified", Vec::new()),
+        };
+        let count = |kind| {
+            changes
+                .iter()
+                .filter(|change| change.kind == kind)
+                .map(|change| change.items.len())
+                .sum::<usize>()
+        };
+        FetchEvent {
+            feed,
+            source,
+            status,
+            duration_ms,
+            failures,
+            parse_failures,
+            new_items: count(ChangeKind::Added),
+            edited_items: count(ChangeKind::Updated),
+            old_key: changes
+                .first()
+                .and_then(|change| change.old_key)
+                .map(|key| key.to_string())
+                .unwrap_or_default(),
+            new_key: changes
+                .first()
+                .map(|change| change.new_key.to_string())
+                .unwrap_or_default(),
+            ..FetchEvent::default()
+        }
+        .log(false);
+        changes
+    }
+}
+
+/// Fields of the "feed fetched" event
+///
+/// Every fetch logs all fields, so the field set is stable. Fields that don't apply are left empty,
+/// or 0 for numbers.
+#[derive(Default)]
+struct FetchEvent<'a> {
+    feed: &'a str,
+    source: &'a str,
+    status: &'a str,
+    duration_ms: u64,
+    http_status: u16,
+    error_kind: &'a str,
+    error: String,
+    failures: u32,
+    backoff_secs: u64,
+    parse_failures: u32,
+    new_items: usize,
+    edited_items: usize,
+    old_key: String,
+    new_key: String,
+}
+
+impl FetchEvent<'_> {
+    fn log(&self, warning: bool) {
+        macro_rules! event {
+            ($level:ident) => {
+                $level!(
+                    feed = self.feed,
+                    source = self.source,
+                    status = self.status,
+                    duration_ms = self.duration_ms,
+                    http_status = self.http_status,
+                    error_kind = self.error_kind,
+                    error = self.error,
+                    failures = self.failures,
+                    backoff_secs = self.backoff_secs,
+                    parse_failures = self.parse_failures,
+                    new_items = self.new_items,
+                    edited_items = self.edited_items,
+                    old_key = self.old_key,
+                    new_key = self.new_key,
+                    "feed fetched"
+                )
+            };
+        }
+        if warning {
+            event!(warn)
+        } else {
+            event!(info)
+        }
+    }
+}
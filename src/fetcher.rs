@@ -1,24 +1,34 @@
+use crate::clock::Clock;
+use croner::Cron;
 use reqwest::header::{
-    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+    HeaderMap, HeaderValue, CACHE_CONTROL, DATE, ETAG, EXPIRES, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, RETRY_AFTER,
 };
 use reqwest::{Response, StatusCode};
+use rss::extension::syndication::UpdatePeriod;
+use std::convert::TryFrom;
 use std::future::Future;
 use std::time::{Duration, Instant};
 use time::format_description::well_known::Rfc2822;
-use time::OffsetDateTime;
+use time::{OffsetDateTime, Time, UtcOffset, Weekday};
 
-/// waiting 6 hours after a 429 should be slow enough for everyone
+/// waiting 6 hours after a 429 without `Retry-After` should be slow enough for everyone
 const DEFAULT_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
 const ONE_SEC: Duration = Duration::from_secs(1);
+const ONE_HOUR: Duration = Duration::from_secs(60 * 60);
+/// upper limit for the backoff after repeated fetch failures
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
 
 pub enum FetchPlanInput {
     Retry {
-        time: Instant,
+        after: Duration,
         headers: CacheHeaders,
     },
     WithCache {
         headers: CacheHeaders,
     },
+    /// the fetch failed, the cache headers from the last successful fetch are kept
+    Failed,
 }
 
 impl FetchPlanInput {
@@ -27,6 +37,7 @@ impl FetchPlanInput {
         match self {
             FetchPlanInput::Retry { headers, .. } => headers,
             FetchPlanInput::WithCache { headers } => headers,
+            FetchPlanInput::Failed => CacheHeaders::default(),
         }
     }
 
@@ -35,10 +46,38 @@ impl FetchPlanInput {
     }
 }
 
+/// The polling intervals for all feeds
+#[derive(Debug, Clone, Copy)]
+pub struct FetchSchedule {
+    /// the normal interval between fetches
+    pub interval: Duration,
+    /// the earliest the server can ask us to fetch again
+    pub min_interval: Duration,
+    /// the latest the server can ask us to fetch again
+    pub max_interval: Duration,
+}
+
+impl FetchSchedule {
+    /// Feeds with a cron expression are fetched at the matching times, the minimum interval is used
+    /// as base for retries
+    pub fn for_cron(&self) -> FetchSchedule {
+        FetchSchedule {
+            interval: self.min_interval,
+            ..*self
+        }
+    }
+
+    fn clamp(&self, delay: Duration) -> Duration {
+        delay.clamp(self.min_interval, self.max_interval.max(self.min_interval))
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct CacheHeaders {
     etag: Option<String>,
     last_modified: Option<OffsetDateTime>,
+    /// how long the response can be cached according to `Cache-Control` or `Expires`
+    freshness: Option<Duration>,
 }
 
 impl CacheHeaders {
@@ -54,6 +93,7 @@ impl CacheHeaders {
         CacheHeaders {
             etag,
             last_modified,
+            freshness: freshness(headers),
         }
     }
 
@@ -82,14 +122,108 @@ impl CacheHeaders {
     }
 }
 
+/// Polling hints set by the publisher of the feed
+#[derive(Debug, Default, Clone)]
+pub struct FetchHints {
+    /// don't fetch more often than this, from `ttl` or `sy:updatePeriod`
+    pub min_interval: Option<Duration>,
+    /// hours (in UTC) during which the feed shouldn't be fetched
+    pub skip_hours: Vec<u8>,
+    pub skip_days: Vec<Weekday>,
+}
+
+impl FetchHints {
+    pub fn from_rss(channel: &rss::Channel) -> Self {
+        let ttl = channel
+            .ttl()
+            .and_then(|ttl| ttl.trim().parse::<u64>().ok())
+            .map(|minutes| Duration::from_secs(minutes * 60));
+        let update_period = channel.syndication_ext().map(|syndication| {
+            let period = match syndication.period() {
+                UpdatePeriod::HOURLY => 60 * 60,
+                UpdatePeriod::DAILY => 24 * 60 * 60,
+                UpdatePeriod::WEEKLY => 7 * 24 * 60 * 60,
+                UpdatePeriod::MONTHLY => 30 * 24 * 60 * 60,
+                UpdatePeriod::YEARLY => 365 * 24 * 60 * 60,
+            };
+            Duration::from_secs(period / u64::from(syndication.frequency().max(1)))
+        });
+        let skip_hours = channel
+            .skip_hours()
+            .iter()
+            .filter_map(|hour| hour.trim().parse::<u8>().ok())
+            .map(|hour| hour % 24)
+            .collect();
+        let skip_days = channel
+            .skip_days()
+            .iter()
+            .filter_map(|day| parse_weekday(day.trim()))
+            .collect();
+        FetchHints {
+            min_interval: ttl.max(update_period),
+            skip_hours,
+            skip_days,
+        }
+    }
+
+    /// Move the time forward until it's outside the skipped hours and days
+    fn next_allowed(&self, time: OffsetDateTime) -> OffsetDateTime {
+        let mut time = time.to_offset(UtcOffset::UTC);
+        // if every hour is skipped, give up after a week
+        for _ in 0..(7 * 24) {
+            if !self.skip_hours.contains(&time.hour()) && !self.skip_days.contains(&time.weekday())
+            {
+                break;
+            }
+            time = time.replace_time(Time::from_hms(time.hour(), 0, 0).unwrap()) + ONE_HOUR;
+        }
+        time
+    }
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    Some(match day.to_ascii_lowercase().as_str() {
+        "monday" => Weekday::Monday,
+        "tuesday" => Weekday::Tuesday,
+        "wednesday" => Weekday::Wednesday,
+        "thursday" => Weekday::Thursday,
+        "friday" => Weekday::Friday,
+        "saturday" => Weekday::Saturday,
+        "sunday" => Weekday::Sunday,
+        _ => return None,
+    })
+}
+
 pub struct FetchPlan {
     pub time: Instant,
     pub headers: CacheHeaders,
+    /// number of consecutive failed fetches
+    pub failures: u32,
+    pub hints: FetchHints,
 }
 
 impl FetchPlan {
-    pub fn is_elapsed(&self) -> bool {
-        Instant::now() >= self.time
+    pub fn is_elapsed(&self, now: Instant) -> bool {
+        now >= self.time
+    }
+
+    /// Delay the planned fetch to respect the polling hints from the feed
+    pub fn apply_hints(&mut self, schedule: &FetchSchedule, clock: &Clock) {
+        let now = clock.now();
+        if let Some(min_interval) = self.hints.min_interval {
+            self.time = self.time.max(now + schedule.clamp(min_interval));
+        }
+        let planned = clock.now_utc() + self.time.saturating_duration_since(now);
+        let allowed = self.hints.next_allowed(planned);
+        self.time += Duration::try_from(allowed - planned).unwrap_or_default();
+    }
+
+    /// Delay the planned fetch to the next time matching the cron expression
+    pub fn apply_cron(&mut self, cron: &Cron, clock: &Clock) {
+        let planned = clock.now_local() + self.time.saturating_duration_since(clock.now());
+        if let Ok(next) = cron.find_next_occurrence(&planned, true) {
+            self.time = clock.instant_at(next);
+        }
     }
 }
 
@@ -98,32 +232,121 @@ impl Default for FetchPlan {
         FetchPlan {
             time: Instant::now(),
             headers: CacheHeaders::default(),
+            failures: 0,
+            hints: FetchHints::default(),
         }
     }
 }
 
 /// plan the next fetch, either on startup or right after we finished the previous fetch
-pub fn next_fetch(base_interval: Duration, last_result: Option<FetchPlanInput>) -> FetchPlan {
-    let now = Instant::now();
+pub fn next_fetch(
+    schedule: &FetchSchedule,
+    previous: FetchPlan,
+    last_result: Option<FetchPlanInput>,
+    now: Instant,
+) -> FetchPlan {
     match last_result {
-        Some(FetchPlanInput::Retry { time, headers }) => FetchPlan {
-            time: now.max(time),
-            headers,
-        },
-        Some(FetchPlanInput::WithCache { headers }) => FetchPlan {
-            time: now + base_interval,
+        Some(FetchPlanInput::Retry { after, headers }) => FetchPlan {
+            time: now + schedule.clamp(after),
             headers,
+            failures: previous.failures,
+            hints: previous.hints,
         },
+        Some(FetchPlanInput::WithCache { headers }) => {
+            // don't poll before the response is stale
+            let delay = match headers.freshness {
+                Some(freshness) => schedule.interval.max(schedule.clamp(freshness)),
+                None => schedule.interval,
+            };
+            FetchPlan {
+                time: now + delay,
+                headers,
+                failures: 0,
+                hints: previous.hints,
+            }
+        }
+        Some(FetchPlanInput::Failed) => {
+            let failures = previous.failures + 1;
+            FetchPlan {
+                time: now + failure_backoff(schedule.interval, failures),
+                headers: previous.headers,
+                failures,
+                hints: previous.hints,
+            }
+        }
         None => FetchPlan {
-            time: now + base_interval,
+            time: now + schedule.interval,
             headers: CacheHeaders::default(),
+            failures: 0,
+            hints: FetchHints::default(),
         },
     }
 }
 
+/// double the interval for every consecutive failure after the first one
+pub fn failure_backoff(base_interval: Duration, failures: u32) -> Duration {
+    let factor = 1u32 << failures.saturating_sub(1).min(16);
+    base_interval
+        .saturating_mul(factor)
+        .min(MAX_FAILURE_BACKOFF.max(base_interval))
+}
+
+/// Parse the `Retry-After` header, either as a number of seconds or as a http date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let time = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    Some(duration_until(time, response_date(headers)))
+}
+
+/// How long a response stays fresh, `Cache-Control: max-age` takes precedence over `Expires`
+fn freshness(headers: &HeaderMap) -> Option<Duration> {
+    let cache_control = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase());
+    let mut max_age = None;
+    for directive in cache_control {
+        if directive == "no-cache" || directive == "no-store" {
+            return None;
+        }
+        if let Some(seconds) = directive.strip_prefix("max-age=") {
+            max_age = seconds
+                .trim_matches('"')
+                .parse()
+                .ok()
+                .map(Duration::from_secs);
+        }
+    }
+    if max_age.is_some() {
+        return max_age;
+    }
+
+    let expires = headers.get(EXPIRES)?.to_str().ok()?;
+    let expires = OffsetDateTime::parse(expires, &Rfc2822).ok()?;
+    Some(duration_until(expires, response_date(headers)))
+}
+
+/// The `Date` of the response, used as the reference for absolute times send by the server
+fn response_date(headers: &HeaderMap) -> OffsetDateTime {
+    headers
+        .get(DATE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|date| OffsetDateTime::parse(date, &Rfc2822).ok())
+        .unwrap_or_else(OffsetDateTime::now_utc)
+}
+
+fn duration_until(time: OffsetDateTime, now: OffsetDateTime) -> Duration {
+    Duration::try_from(time - now).unwrap_or_default()
+}
+
 pub enum FetchResponse<T, E> {
     Retry {
-        time: Instant,
+        after: Duration,
         headers: CacheHeaders,
     },
     Ok {
@@ -143,17 +366,17 @@ impl<T, E> FetchResponse<T, E> {
     #[allow(dead_code)]
     pub fn plan(self) -> FetchPlanInput {
         match self {
-            FetchResponse::Retry { time, headers } => FetchPlanInput::Retry { time, headers },
+            FetchResponse::Retry { after, headers } => FetchPlanInput::Retry { after, headers },
             FetchResponse::Ok { headers, .. } => FetchPlanInput::WithCache { headers },
             FetchResponse::NotModified { headers, .. } => FetchPlanInput::WithCache { headers },
-            FetchResponse::Error { headers, .. } => FetchPlanInput::WithCache { headers },
+            FetchResponse::Error { .. } => FetchPlanInput::Failed,
         }
     }
 
     pub fn into_result(self) -> (Result<Option<T>, E>, FetchPlanInput) {
         match self {
-            FetchResponse::Retry { time, headers } => {
-                (Ok(None), FetchPlanInput::Retry { time, headers })
+            FetchResponse::Retry { after, headers } => {
+                (Ok(None), FetchPlanInput::Retry { after, headers })
             }
             FetchResponse::Ok { headers, response } => {
                 (Ok(Some(response)), FetchPlanInput::WithCache { headers })
@@ -161,9 +384,7 @@ impl<T, E> FetchResponse<T, E> {
             FetchResponse::NotModified { headers } => {
                 (Ok(None), FetchPlanInput::WithCache { headers })
             }
-            FetchResponse::Error { headers, error } => {
-                (Err(error), FetchPlanInput::WithCache { headers })
-            }
+            FetchResponse::Error { error, .. } => (Err(error), FetchPlanInput::Failed),
         }
     }
 }
@@ -173,16 +394,18 @@ impl<E> FetchResponse<Response, E> {
         match result {
             Ok(response) => {
                 let cache_header = CacheHeaders::from_headers(response.headers());
+                let retry_after = retry_after(response.headers());
                 if response.status() == StatusCode::TOO_MANY_REQUESTS {
-                    let after = response
-                        .headers()
-                        .get(RETRY_AFTER)
-                        .and_then(|header| header.to_str().ok())
-                        .and_then(|str| str.parse::<u64>().ok())
-                        .map(Duration::from_secs)
-                        .unwrap_or(DEFAULT_BACKOFF);
+                    let after = retry_after.unwrap_or(DEFAULT_BACKOFF);
                     FetchResponse::Retry {
-                        time: Instant::now() + after + ONE_SEC,
+                        after: after + ONE_SEC,
+                        headers: cache_header,
+                    }
+                } else if let (StatusCode::SERVICE_UNAVAILABLE, Some(after)) =
+                    (response.status(), retry_after)
+                {
+                    FetchResponse::Retry {
+                        after: after + ONE_SEC,
                         headers: cache_header,
                     }
                 } else if response.status() == StatusCode::NOT_MODIFIED {
@@ -237,7 +460,7 @@ impl<T, E> FetchResponse<T, E> {
         F: Fn(T) -> Fut,
     {
         match self {
-            FetchResponse::Retry { time, headers } => FetchResponse::Retry { time, headers },
+            FetchResponse::Retry { after, headers } => FetchResponse::Retry { after, headers },
             FetchResponse::Ok { headers, response } => FetchResponse::Ok {
                 headers,
                 response: f(response).await,
@@ -251,7 +474,7 @@ impl<T, E> FetchResponse<T, E> {
         F: Fn(E) -> U,
     {
         match self {
-            FetchResponse::Retry { time, headers } => FetchResponse::Retry { time, headers },
+            FetchResponse::Retry { after, headers } => FetchResponse::Retry { after, headers },
             FetchResponse::Ok { response, headers } => FetchResponse::Ok { response, headers },
             FetchResponse::NotModified { headers } => FetchResponse::NotModified { headers },
             FetchResponse::Error { error, headers } => FetchResponse::Error {
@@ -265,7 +488,7 @@ impl<T, E> FetchResponse<T, E> {
 impl<T, E> FetchResponse<Result<T, E>, E> {
     pub fn flatten(self) -> FetchResponse<T, E> {
         match self {
-            FetchResponse::Retry { time, headers } => FetchResponse::Retry { time, headers },
+            FetchResponse::Retry { after, headers } => FetchResponse::Retry { after, headers },
             FetchResponse::Ok {
                 headers,
                 response: Ok(response),
@@ -275,10 +498,114 @@ impl<T, E> FetchResponse<Result<T, E>, E> {
                 response: Err(error),
             } => FetchResponse::Error { error, headers },
             FetchResponse::NotModified { headers } => FetchResponse::NotModified { headers },
-            FetchResponse::Error { error, headers } => FetchResponse::Error {
-                error,
-                headers,
-            },
+            FetchResponse::Error { error, headers } => FetchResponse::Error { error, headers },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::format_description::well_known::Rfc3339;
+
+    fn time(time: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(time, &Rfc3339).unwrap()
+    }
+
+    fn hints(skip_hours: &[u8], skip_days: &[Weekday]) -> FetchHints {
+        FetchHints {
+            min_interval: None,
+            skip_hours: skip_hours.to_vec(),
+            skip_days: skip_days.to_vec(),
         }
     }
+
+    #[test]
+    fn test_next_allowed_not_skipped() {
+        let hints = hints(&[3], &[Weekday::Sunday]);
+        let monday = time("2024-01-01T10:15:00Z");
+        assert_eq!(monday, hints.next_allowed(monday));
+    }
+
+    #[test]
+    fn test_next_allowed_skip_hours_over_midnight() {
+        let hints = hints(&[22, 23, 0, 1], &[]);
+        assert_eq!(
+            time("2024-01-02T02:00:00Z"),
+            hints.next_allowed(time("2024-01-01T22:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_allowed_skip_days_over_week() {
+        let hints = hints(&[], &[Weekday::Saturday, Weekday::Sunday]);
+        assert_eq!(
+            time("2024-01-08T00:00:00Z"),
+            hints.next_allowed(time("2024-01-06T10:15:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_allowed_skip_days_and_hours() {
+        let hints = hints(&[0, 1], &[Weekday::Sunday]);
+        assert_eq!(
+            time("2024-01-08T02:00:00Z"),
+            hints.next_allowed(time("2024-01-07T20:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_allowed_in_utc() {
+        let hints = hints(&[10], &[]);
+        assert_eq!(
+            time("2024-01-01T11:00:00Z"),
+            hints.next_allowed(time("2024-01-01T12:30:00+02:00"))
+        );
+    }
+
+    #[test]
+    fn test_next_allowed_everything_skipped() {
+        let hints = hints(&(0..24).collect::<Vec<_>>(), &[]);
+        assert_eq!(
+            time("2024-01-08T10:00:00Z"),
+            hints.next_allowed(time("2024-01-01T10:15:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_hints_from_rss() {
+        let channel = rss::Channel::read_from(
+            r#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+            <channel>
+                <title>feed</title><link>https://example.com</link><description>feed</description>
+                <ttl>30</ttl>
+                <skipHours><hour>0</hour><hour>24</hour><hour>invalid</hour></skipHours>
+                <skipDays><day>Saturday</day><day>sunday</day></skipDays>
+                <sy:updatePeriod>daily</sy:updatePeriod>
+                <sy:updateFrequency>2</sy:updateFrequency>
+            </channel>
+            </rss>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let hints = FetchHints::from_rss(&channel);
+        // the update period allows a longer interval than the ttl
+        assert_eq!(Some(Duration::from_secs(12 * 60 * 60)), hints.min_interval);
+        assert_eq!(vec![0, 0], hints.skip_hours);
+        assert_eq!(vec![Weekday::Saturday, Weekday::Sunday], hints.skip_days);
+    }
+
+    #[test]
+    fn test_hints_from_rss_ttl() {
+        let channel = rss::Channel::read_from(
+            r#"<?xml version="1.0"?><rss version="2.0"><channel><title>feed</title><link>https://example.com</link><description>feed</description><ttl>30</ttl></channel></rss>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let hints = FetchHints::from_rss(&channel);
+        assert_eq!(Some(Duration::from_secs(30 * 60)), hints.min_interval);
+        assert!(hints.skip_hours.is_empty());
+        assert!(hints.skip_days.is_empty());
+    }
 }
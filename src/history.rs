@@ -0,0 +1,305 @@
+use crate::change::{ChangeKind, TagDiff};
+use crate::config::HistoryConfig;
+use crate::error::HistoryError;
+use crate::item::FeedItem;
+use serde::{Deserialize, Serialize};
+use std::fs::{read_to_string, rename, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use tracing::warn;
+
+/// A single triggered action and its outcome
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub feed: String,
+    pub action: String,
+    pub hook: String,
+    /// name of the source the feed was fetched from
+    #[serde(default)]
+    pub source: String,
+    /// whether the trigger was for added or edited items
+    #[serde(default)]
+    pub change: ChangeKind,
+    /// key of the feed before the change, not set for triggers on start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_key: Option<String>,
+    #[serde(default)]
+    pub new_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<TagDiff>,
+    pub items: Vec<HistoryItem>,
+    /// `success` or `failed`
+    pub status: String,
+    /// method and url of the (last) request send
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryItem {
+    /// stable key of the item, as used to detect new items
+    #[serde(default)]
+    pub key: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl From<&FeedItem> for HistoryItem {
+    fn from(item: &FeedItem) -> Self {
+        HistoryItem {
+            key: item.key.to_string(),
+            title: item.display_title().into(),
+            link: item.link.clone(),
+        }
+    }
+}
+
+/// Append only log of triggers, stored as json lines
+pub struct History {
+    path: PathBuf,
+    max_entries: usize,
+    max_age: Option<time::Duration>,
+    entries: usize,
+}
+
+impl History {
+    /// Open the history file, removing entries past the retention limits
+    pub fn open(config: &HistoryConfig) -> Result<Self, HistoryError> {
+        let mut history = History {
+            path: config.path.clone(),
+            max_entries: config.max_entries,
+            max_age: config.max_age(),
+            entries: 0,
+        };
+        history.prune()?;
+        Ok(history)
+    }
+
+    pub fn append(&mut self, entry: &HistoryEntry) -> Result<(), HistoryError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(|error| HistoryError::io(error, &self.path))?;
+        self.entries += 1;
+
+        // allow some slack so the file isn't rewritten for every trigger
+        if self.entries > self.max_entries + self.max_entries / 10 {
+            self.prune()?;
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self) -> Result<(), HistoryError> {
+        let mut entries = read(&self.path)?;
+        if let Some(max_age) = self.max_age {
+            let cutoff = OffsetDateTime::now_utc() - max_age;
+            entries.retain(|entry| entry.time >= cutoff);
+        }
+        let excess = entries.len().saturating_sub(self.max_entries);
+        entries.drain(..excess);
+
+        let mut content = Vec::new();
+        for entry in &entries {
+            content.extend(serde_json::to_vec(entry)?);
+            content.push(b'\n');
+        }
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, content)
+            .and_then(|_| rename(&temp, &self.path))
+            .map_err(|error| HistoryError::io(error, &self.path))?;
+        self.entries = entries.len();
+        Ok(())
+    }
+}
+
+/// Read all entries from a history file, oldest first
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(HistoryError::io(error, path)),
+    };
+    Ok(content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                warn!(%error, "skipping invalid history entry");
+                None
+            }
+        })
+        .collect())
+}
+
+/// Get the latest entries, optionally only for a single feed, newest first
+pub fn query(
+    path: &Path,
+    feed: Option<&str>,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>, HistoryError> {
+    Ok(read(path)?
+        .into_iter()
+        .rev()
+        .filter(|entry| feed.map_or(true, |feed| entry.feed == feed))
+        .take(limit)
+        .collect())
+}
+
+/// Print the history for the `history` subcommand, newest first
+pub fn print(
+    config: &HistoryConfig,
+    feed: Option<&str>,
+    limit: usize,
+    json: bool,
+) -> Result<(), HistoryError> {
+    for entry in query(&config.path, feed, limit)? {
+        if json {
+            println!("{}", serde_json::to_string(&entry)?);
+        } else {
+            let titles: Vec<&str> = entry.items.iter().map(|item| item.title.as_str()).collect();
+            let time = entry
+                .time
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default();
+            print!(
+                "{} {} {} {} [{}]",
+                time,
+                entry.status,
+                entry.action,
+                entry.hook,
+                titles.join(", ")
+            );
+            if let Some(status) = entry.http_status {
+                print!(" {}", status);
+            }
+            print!(" {}ms", entry.duration_ms);
+            if let Some(error) = &entry.error {
+                print!(" {}", error);
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use time::format_description::well_known::Rfc3339;
+
+    fn entry(age: time::Duration, title: &str) -> HistoryEntry {
+        let time = (OffsetDateTime::now_utc() - age).format(&Rfc3339).unwrap();
+        serde_json::from_value(json!({
+            "time": time,
+            "feed": "https://example.com/feed.xml",
+            "action": "webhook",
+            "hook": "https://example.com/hook",
+            "items": [{ "key": "v1:00", "title": title }],
+            "status": "success",
+            "duration_ms": 10,
+        }))
+        .unwrap()
+    }
+
+    fn titles(path: &Path) -> Vec<String> {
+        read(path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.items[0].title.clone())
+            .collect()
+    }
+
+    fn history_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "rss-webhook-trigger-history-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+
+    fn config(path: &Path, retention: &str) -> HistoryConfig {
+        toml::from_str(&format!("path = {:?}\n{}", path, retention)).unwrap()
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let path = history_path("entries");
+        let mut history = History::open(&config(&path, "max_entries = 3")).unwrap();
+        for title in ["v1", "v2", "v3", "v4", "v5"] {
+            history.append(&entry(time::Duration::ZERO, title)).unwrap();
+        }
+        assert_eq!(vec!["v3", "v4", "v5"], titles(&path));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_max_entries_slack() {
+        let path = history_path("slack");
+        let mut history = History::open(&config(&path, "max_entries = 10")).unwrap();
+        for i in 0..11 {
+            history
+                .append(&entry(time::Duration::ZERO, &format!("v{}", i)))
+                .unwrap();
+        }
+        // the file is only rewritten once it's more than 10% over the limit
+        assert_eq!(11, titles(&path).len());
+        history.append(&entry(time::Duration::ZERO, "v11")).unwrap();
+        assert_eq!(10, titles(&path).len());
+        assert_eq!(Some("v2"), titles(&path).first().map(String::as_str));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_max_age() {
+        let path = history_path("age");
+        let mut history = History::open(&config(&path, "max_age = 7")).unwrap();
+        history
+            .append(&entry(time::Duration::days(8), "v1"))
+            .unwrap();
+        history
+            .append(&entry(time::Duration::days(6), "v2"))
+            .unwrap();
+        history.append(&entry(time::Duration::ZERO, "v3")).unwrap();
+        assert_eq!(3, titles(&path).len());
+
+        // entries past the maximum age are removed when the history is opened
+        History::open(&config(&path, "max_age = 7")).unwrap();
+        assert_eq!(vec!["v2", "v3"], titles(&path));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_query() {
+        let path = history_path("query");
+        let mut history = History::open(&config(&path, "")).unwrap();
+        for title in ["v1", "v2", "v3"] {
+            history.append(&entry(time::Duration::ZERO, title)).unwrap();
+        }
+        let entries = query(&path, Some("https://example.com/feed.xml"), 2).unwrap();
+        let titles: Vec<_> = entries.iter().map(|entry| &entry.items[0].title).collect();
+        assert_eq!(vec!["v3", "v2"], titles);
+        assert!(query(&path, Some("https://example.com/other.xml"), 2)
+            .unwrap()
+            .is_empty());
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -1,24 +1,26 @@
+use crate::config::FeedAuth;
 use crate::error::HubError;
 use crate::fetcher::{CacheHeaders, FetchResponse};
-use reqwest::{Client};
+use crate::FETCHER_USER_AGENT;
 use reqwest::header::{HeaderValue, USER_AGENT};
+use reqwest::Client;
 use serde::Deserialize;
 use time::OffsetDateTime;
 use tracing::instrument;
-use crate::FETCHER_USER_AGENT;
 
-#[instrument(skip(client))]
+#[instrument(skip(client, auth))]
 pub async fn tags(
     client: &Client,
     user: &str,
     repo: &str,
+    auth: &FeedAuth,
     cache_headers: &CacheHeaders,
 ) -> FetchResponse<Vec<HubTag>, HubError> {
-    let result = client
-        .get(format!(
+    let result = auth
+        .apply(client.get(format!(
             "https://hub.docker.com/v2/repositories/{}/{}/tags",
             user, repo
-        ))
+        )))
         .headers(cache_headers.headers())
         .header(USER_AGENT, HeaderValue::from_static(FETCHER_USER_AGENT))
         .send()
@@ -32,9 +34,13 @@ pub async fn tags(
                 .text()
                 .await
                 .map_err(HubError::Network)
-                .and_then(|text| serde_json::from_str::<HubTagResponse>(&text).map_err(HubError::Parse))
+                .and_then(|text| {
+                    serde_json::from_str::<HubTagResponse>(&text).map_err(HubError::Parse)
+                })
                 .map(|result| result.results)
-        }).await.flatten()
+        })
+        .await
+        .flatten()
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,6 +51,7 @@ pub struct HubTagResponse {
 #[derive(Debug, Deserialize)]
 pub struct HubTag {
     pub id: u64,
+    pub name: String,
     #[serde(with = "time::serde::rfc3339")]
     pub last_updated: OffsetDateTime,
 }
@@ -0,0 +1,239 @@
+use crate::fetcher::FetchHints;
+use crate::hub::HubTag;
+use crate::key::Key;
+use crate::media::{atom_enclosures, media_rss, rss_enclosures, Enclosure};
+use serde::Serialize;
+use std::collections::HashMap;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::OffsetDateTime;
+
+/// The state of a feed after fetching it
+#[derive(Debug)]
+pub struct FeedSnapshot {
+    /// key used to detect changes in the feed
+    pub key: Key,
+    /// all items in the feed, newest first
+    pub items: Vec<FeedItem>,
+    /// polling hints from the publisher
+    pub hints: FetchHints,
+    /// tag names with the key of the tagged image, for docker hub repositories
+    pub tags: Option<HashMap<String, Key>>,
+}
+
+impl FeedSnapshot {
+    /// The key and revision of every item
+    pub fn item_revisions(&self) -> HashMap<Key, Key> {
+        self.items
+            .iter()
+            .map(|item| (item.key, item.revision))
+            .collect()
+    }
+
+    /// Get the items that were in the feed before, but have been edited since
+    pub fn edited_items(&self, seen: &HashMap<Key, Key>) -> Vec<FeedItem> {
+        self.items
+            .iter()
+            .filter(|item| {
+                seen.get(&item.key)
+                    .is_some_and(|revision| *revision != item.revision)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get the items that weren't in the feed before
+    pub fn new_items(&self, seen: &HashMap<Key, Key>) -> Vec<FeedItem> {
+        self.items
+            .iter()
+            .filter(|item| !seen.contains_key(&item.key))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the items that weren't in the feed before
+    ///
+    /// For docker hub repositories a changed key means a tag was pushed, even if it points to
+    /// an image that was seen before, so this falls back to the newest tag if there are no new items.
+    pub fn into_new_items(self, seen: &HashMap<Key, Key>) -> Vec<FeedItem> {
+        let new = self.new_items(seen);
+        if new.is_empty() && self.tags.is_some() {
+            self.items.into_iter().take(1).collect()
+        } else {
+            new
+        }
+    }
+
+    /// Key for a feed without items
+    pub fn empty_key() -> Key {
+        Key::builder("empty_feed").finish()
+    }
+}
+
+/// The parts of a feed item that are passed on to the actions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FeedItem {
+    /// key identifying the item within the feed
+    #[serde(skip)]
+    pub key: Key,
+    /// key of the item content, changes when the item is edited
+    #[serde(skip)]
+    pub revision: Key,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    #[serde(
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub published: Option<OffsetDateTime>,
+    /// rss enclosures or atom enclosure links
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enclosures: Vec<Enclosure>,
+    /// Media RSS `media:content` files
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<Enclosure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+}
+
+impl FeedItem {
+    pub fn from_rss(key: Key, item: &rss::Item) -> Self {
+        let revision = Key::builder("rss_item_revision")
+            .field(item.title().unwrap_or_default())
+            .field(item.link().unwrap_or_default())
+            .field(item.description().unwrap_or_default())
+            .field(item.content().unwrap_or_default())
+            .field(item.pub_date().unwrap_or_default())
+            .finish();
+        let (media, thumbnail) = media_rss(item.extensions());
+        FeedItem {
+            key,
+            revision,
+            title: item.title().map(String::from),
+            link: item.link().map(String::from),
+            summary: item.description().map(String::from),
+            published: item
+                .pub_date()
+                .and_then(|date| OffsetDateTime::parse(date, &Rfc2822).ok()),
+            enclosures: rss_enclosures(item),
+            media,
+            thumbnail,
+        }
+    }
+
+    pub fn from_atom(key: Key, entry: &atom_syndication::Entry) -> Self {
+        let link = entry
+            .links()
+            .iter()
+            .find(|link| link.rel() == "alternate")
+            .or_else(|| entry.links().first())
+            .map(|link| link.href().to_string());
+        let revision = Key::builder("atom_entry_revision")
+            .field(entry.updated())
+            .field(entry.title())
+            .field(entry.summary().unwrap_or_default())
+            .field(
+                entry
+                    .content()
+                    .and_then(|content| content.value())
+                    .unwrap_or_default(),
+            )
+            .finish();
+        let (media, thumbnail) = media_rss(entry.extensions());
+        FeedItem {
+            key,
+            revision,
+            title: Some(entry.title().to_string()),
+            link,
+            summary: entry
+                .summary()
+                .or_else(|| entry.content().and_then(|content| content.value()))
+                .map(String::from),
+            published: entry
+                .published()
+                .and_then(|date| OffsetDateTime::parse(date, &Rfc3339).ok())
+                .or_else(|| OffsetDateTime::parse(entry.updated(), &Rfc3339).ok()),
+            enclosures: atom_enclosures(entry),
+            media,
+            thumbnail,
+        }
+    }
+
+    pub fn from_hub_tag(key: Key, user: &str, repo: &str, tag: &HubTag) -> Self {
+        FeedItem {
+            key,
+            // the key already changes when the tag is moved
+            revision: key,
+            title: Some(format!("{}/{}:{}", user, repo, tag.name)),
+            link: Some(format!("https://hub.docker.com/r/{}/{}/tags", user, repo)),
+            summary: None,
+            published: Some(tag.last_updated),
+            ..FeedItem::default()
+        }
+    }
+
+    /// The url of the first enclosure or media file
+    pub fn enclosure_url(&self) -> Option<&str> {
+        self.enclosures
+            .iter()
+            .chain(&self.media)
+            .next()
+            .map(|enclosure| enclosure.url.as_str())
+    }
+
+    /// Check if any enclosure or media file has one of the mime types
+    pub fn has_enclosure_type(&self, patterns: &[String]) -> bool {
+        self.enclosures
+            .iter()
+            .chain(&self.media)
+            .any(|enclosure| enclosure.matches(patterns))
+    }
+
+    /// A short title for the item, falling back to the link if no title is set
+    pub fn display_title(&self) -> &str {
+        self.title
+            .as_deref()
+            .or(self.link.as_deref())
+            .unwrap_or("New feed item")
+    }
+
+    /// The summary with any html stripped, limited to `max_length` characters
+    pub fn plain_summary(&self, max_length: usize) -> Option<String> {
+        let summary = strip_html(self.summary.as_deref()?);
+        if summary.is_empty() {
+            return None;
+        }
+        if summary.chars().count() > max_length {
+            let mut truncated: String = summary.chars().take(max_length - 1).collect();
+            truncated.push('…');
+            Some(truncated)
+        } else {
+            Some(summary)
+        }
+    }
+}
+
+/// Naively strip html tags and collapse whitespace, good enough for notification previews
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,39 @@
+pub mod action;
+pub mod change;
+pub(crate) mod ci;
+pub mod client;
+pub(crate) mod clock;
+pub mod config;
+pub mod error;
+pub(crate) mod exec;
+pub(crate) mod feed;
+pub mod fetcher;
+pub mod history;
+pub(crate) mod hub;
+pub mod item;
+pub mod key;
+pub mod logging;
+pub mod media;
+pub(crate) mod mqtt;
+pub(crate) mod queue;
+pub mod runner;
+pub(crate) mod schedule;
+pub(crate) mod secret;
+pub(crate) mod signature;
+pub(crate) mod sink;
+pub(crate) mod smtp;
+pub mod source;
+pub mod status;
+pub(crate) mod template;
+
+/// Clock used by the runner, can be replaced to drive the runner in tests
+pub use clock::Clock;
+
+pub const FETCHER_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CARGO_PKG_REPOSITORY"),
+    ")"
+);
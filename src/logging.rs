@@ -0,0 +1,47 @@
+use crate::config::{LogConfig, LogFormat};
+use crate::error::LogError;
+use std::env;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Registry};
+
+/// Log options given on the command line, these override the config file
+#[derive(Debug, Default)]
+pub struct LogOptions {
+    pub format: Option<LogFormat>,
+    pub level: Option<String>,
+}
+
+/// Setup the global logger
+///
+/// The level is taken from the command line, `RUST_LOG` or the config file, in that order,
+/// the per-module levels from the config are applied on top of that.
+pub fn init(config: &LogConfig, options: LogOptions) -> Result<(), LogError> {
+    let level = options
+        .level
+        .or_else(|| env::var("RUST_LOG").ok())
+        .or_else(|| config.level.clone())
+        .unwrap_or_else(|| "info".into());
+    let mut filter = EnvFilter::try_new(&level)?;
+    for (module, level) in &config.modules {
+        filter = filter.add_directive(format!("{}={}", module, level).parse()?);
+    }
+
+    let registry = Registry::default().with(filter);
+    match options.format.unwrap_or(config.format) {
+        LogFormat::Text => registry.with(fmt::layer()).init(),
+        LogFormat::Json => registry
+            .with(
+                fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(false)
+                    .with_span_list(false),
+            )
+            .init(),
+        LogFormat::Journald => registry
+            .with(tracing_journald::layer().map_err(LogError::Journald)?)
+            .init(),
+    }
+    Ok(())
+}
@@ -1,45 +1,84 @@
-mod config;
-mod error;
-mod fetcher;
-mod hub;
-
-use crate::config::{Config, FeedConfig};
-use crate::error::{FetchError, FetchFeedError, HubError, ParseFeedError};
-use crate::fetcher::{next_fetch, CacheHeaders, FetchPlan, FetchResponse};
 use main_error::MainResult;
-use reqwest::{Client, Response};
-use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
-use std::future::ready;
-use std::hash::{Hash, Hasher};
-use std::str::FromStr;
-use std::time::{Duration};
-use reqwest::header::{HeaderValue, USER_AGENT};
-use syndication::Feed;
+use rss_webhook_trigger::action::ActionRegistry;
+use rss_webhook_trigger::client::ClientPool;
+use rss_webhook_trigger::config::Config;
+use rss_webhook_trigger::error::HistoryError;
+use rss_webhook_trigger::history::{self, History};
+use rss_webhook_trigger::logging::{self, LogOptions};
+use rss_webhook_trigger::runner::Runner;
+use rss_webhook_trigger::source::SourceRegistry;
+use rss_webhook_trigger::status::StatusServer;
 use tokio::select;
 use tokio::signal::ctrl_c;
-use tokio::time::sleep;
-use tracing::{debug, error, info, instrument, warn};
-
-const FETCHER_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " (", env!("CARGO_PKG_REPOSITORY"), ")");
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> MainResult {
-    tracing_subscriber::fmt::init();
     let mut args = std::env::args();
     let bin = args.next().unwrap();
 
-    let file = match args.next() {
+    let mut log_options = LogOptions::default();
+    let mut file = None;
+    let mut history_command = false;
+    let mut history_feed = None;
+    let mut history_limit = 20;
+    let mut history_json = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--log-format" => {
+                log_options.format = Some(args.next().unwrap_or_default().parse()?);
+            }
+            "--log-level" => log_options.level = args.next(),
+            "history" if file.is_none() => history_command = true,
+            "--feed" if history_command => history_feed = args.next(),
+            "--limit" if history_command => {
+                history_limit = args.next().unwrap_or_default().parse()?
+            }
+            "--json" if history_command => history_json = true,
+            _ if arg.starts_with("--") || file.is_some() => {
+                eprintln!("Unexpected argument {}", arg);
+                usage(&bin);
+                std::process::exit(1);
+            }
+            _ => file = Some(arg),
+        }
+    }
+    let file = match file {
         Some(file) => file,
         None => {
-            eprintln!("Usage {} <config file>", bin);
+            usage(&bin);
             return Ok(());
         }
     };
 
     let config = Config::from_file(&file)?;
+    logging::init(&config.log, log_options)?;
+
+    if history_command {
+        let history = config.history.as_ref().ok_or(HistoryError::NotConfigured)?;
+        history::print(
+            history,
+            history_feed.as_deref(),
+            history_limit,
+            history_json,
+        )?;
+        return Ok(());
+    }
 
-    info!("Running rss trigger for {} feeds", config.feed.len());
+    let clients = ClientPool::new(&config)?;
+    let history = config.history.as_ref().map(History::open).transpose()?;
+
+    if let Some(status) = &config.status {
+        let server = StatusServer::bind(status, config.history.as_ref()).await?;
+        tokio::spawn(server.serve());
+    }
+
+    let sources = SourceRegistry::default();
+    let actions = ActionRegistry::default();
+    let feeds = config.feed.len();
+    let runner = Runner::new(config, clients, sources, actions, history)?;
+
+    info!("Running rss trigger for {} feeds", feeds);
 
     let ctrl_c = async {
         ctrl_c().await.ok();
@@ -47,195 +86,18 @@ async fn main() -> MainResult {
 
     select! {
         _ = ctrl_c => {},
-        _ = main_loop(config) => {}
+        _ = runner.run() => {}
     }
     Ok(())
 }
 
-async fn main_loop(config: Config) {
-    let mut fetcher = FeedFetcher::new(config.interval());
-
-    loop {
-        for feed in config.feed.iter() {
-            match fetcher.check_feed_updated(&feed.feed).await {
-                Ok(true) => {
-                    trigger(&fetcher.client, feed).await;
-                }
-                Err(e) => error!(error = ?e, feed = feed.feed, "failed to check feed"),
-                Ok(false) => {}
-            }
-        }
-
-        sleep(config.interval()).await;
-    }
-}
-
-#[instrument(skip_all, fields(feed = feed.feed))]
-async fn trigger(client: &Client, feed: &FeedConfig) {
-    info!("Triggering hook");
-    let mut req = client
-        .post(&feed.hook)
-        .header("user-agent", "rss-webhook-trigger");
-    for (key, value) in &feed.headers {
-        req = req.header(key, value);
-    }
-    if !feed.body.is_null() {
-        req = req.json(&feed.body);
-    }
-    debug!(request = ?req, "sending trigger request");
-    if let Err(e) = req.send().await.and_then(|res| res.error_for_status()) {
-        error!("{:#}", e);
-    }
-}
-
-pub struct FeedFetcher {
-    client: Client,
-    base_interval: Duration,
-    cache: HashMap<String, u64>,
-    fetch_plans: HashMap<String, FetchPlan>,
-}
-
-impl FeedFetcher {
-    pub fn new(interval: Duration) -> Self {
-        FeedFetcher {
-            client: Client::default(),
-            base_interval: interval,
-            cache: HashMap::default(),
-            fetch_plans: HashMap::default(),
-        }
-    }
-
-    pub fn should_update(&self, feed: &str) -> bool {
-        match self.fetch_plans.get(feed) {
-            Some(plan) => plan.is_elapsed(),
-            None => true,
-        }
-    }
-
-    #[instrument(skip(self))]
-    pub async fn check_feed_updated(&mut self, feed: &str) -> Result<bool, FetchError> {
-        if !self.should_update(feed) {
-            warn!("skipping feed util rate limited expires");
-            return Ok(false);
-        }
-        let plan = self.fetch_plans.remove(feed).unwrap_or_default();
-
-        let fetch_result = self.get_feed_key(feed, &plan.headers).await;
-        let (result, new_plan) = fetch_result.into_result();
-        let is_retry = new_plan.is_retry();
-        self.fetch_plans.insert(feed.into(), next_fetch(self.base_interval, Some(new_plan)));
-        let new_key = result?;
-
-        Ok(match (self.cache.get_mut(feed), new_key) {
-            (Some(cached), Some(new_key)) => {
-                debug!(cached, new_key, "checked existing feed");
-                if new_key != *cached {
-                    *cached = new_key;
-                    info!("feed updated");
-                    true
-                } else {
-                    false
-                }
-            }
-            (None, Some(new_key)) => {
-                debug!(feed, "new feed");
-                self.cache.insert(feed.into(), new_key);
-
-                // don't trigger the actions on start
-                false
-            }
-            (_, None) if is_retry => {
-                warn!("rate limited by server");
-                false
-            }
-            (_, None) => {
-                debug!("not modified");
-                false
-            }
-        })
-    }
-
-    #[instrument(skip(self))]
-    async fn get_feed_key(
-        &self,
-        feed: &str,
-        cache_headers: &CacheHeaders,
-    ) -> FetchResponse<u64, FetchError> {
-        if let Some(hub) = feed.strip_prefix("docker-hub://") {
-            if let Some((user, repo)) = hub.split_once('/') {
-                hub::tags(&self.client, user, repo, cache_headers)
-                    .await
-                    .map(|tags| {
-                        let mut hasher = DefaultHasher::new();
-                        for tag in tags {
-                            tag.id.hash(&mut hasher);
-                            tag.last_updated.hash(&mut hasher);
-                        }
-                        ready(hasher.finish())
-                    }).await
-                    .map_err(FetchError::Hub)
-            } else {
-                FetchResponse::Error {
-                    error: HubError::InvalidFormat.into(),
-                    headers: CacheHeaders::default(),
-                }
-            }
-        } else {
-            self.get_rss_feed_key(feed, cache_headers)
-                .await
-                .map_err(FetchError::Feed)
-        }
-    }
-
-    #[instrument(skip(self))]
-    async fn get_rss_feed_key(
-        &self,
-        feed: &str,
-        cache_headers: &CacheHeaders,
-    ) -> FetchResponse<u64, FetchFeedError> {
-        let response = self
-            .client
-            .get(feed)
-            .headers(cache_headers.headers())
-            .header(USER_AGENT, HeaderValue::from_static(FETCHER_USER_AGENT))
-            .send()
-            .await;
-
-        let plan_result = FetchResponse::from_result(response);
-        plan_result
-            .map_err(FetchFeedError::Network)
-            .check_status_code(FetchFeedError::ClientError, FetchFeedError::ServerError)
-            .map(parse_rss_response)
-            .await
-            .flatten()
-    }
-}
-
-async fn parse_rss_response(response: Response) -> Result<u64, FetchFeedError> {
-    let content = response.text().await?;
-    let channel = Feed::from_str(&content).map_err(ParseFeedError::Parse)?;
-
-    let mut hasher = DefaultHasher::new();
-
-    match channel {
-        Feed::RSS(channel) => {
-            let item = channel.items.first().ok_or(ParseFeedError::Empty)?;
-
-            if let Some(guid) = item.guid() {
-                guid.value.hash(&mut hasher);
-            } else if let Some(date) = item.pub_date() {
-                date.hash(&mut hasher);
-            } else if let Some(link) = item.link() {
-                link.hash(&mut hasher);
-            } else {
-                return Err(ParseFeedError::MissingKey.into());
-            }
-        }
-        Feed::Atom(channel) => {
-            let item = channel.entries().first().ok_or(ParseFeedError::Empty)?;
-            item.id().hash(&mut hasher);
-        }
-    }
-
-    Ok(hasher.finish())
+fn usage(bin: &str) {
+    eprintln!(
+        "Usage {} [--log-format text|json|journald] [--log-level <filter>] <config file>",
+        bin
+    );
+    eprintln!(
+        "      {} history [--feed <url>] [--limit <count>] [--json] <config file>",
+        bin
+    );
 }
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A media file attached to a feed item
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Enclosure {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// size in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+}
+
+impl Enclosure {
+    fn new(url: &str, mime_type: Option<&str>, length: Option<&str>) -> Self {
+        Enclosure {
+            url: url.into(),
+            mime_type: mime_type
+                .filter(|mime_type| !mime_type.is_empty())
+                .map(String::from),
+            length: length.and_then(|length| length.trim().parse().ok()),
+        }
+    }
+
+    /// Check if the mime type matches any of the patterns, such as `audio/mpeg` or `video/*`
+    pub fn matches(&self, patterns: &[String]) -> bool {
+        let mime_type = match &self.mime_type {
+            Some(mime_type) => mime_type.to_ascii_lowercase(),
+            None => return false,
+        };
+        patterns.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_suffix("/*") {
+                Some(kind) => mime_type.split('/').next() == Some(kind),
+                None => mime_type == pattern,
+            }
+        })
+    }
+}
+
+pub fn rss_enclosures(item: &rss::Item) -> Vec<Enclosure> {
+    item.enclosure()
+        .map(|enclosure| {
+            Enclosure::new(
+                enclosure.url(),
+                Some(enclosure.mime_type()),
+                Some(enclosure.length()),
+            )
+        })
+        .into_iter()
+        .collect()
+}
+
+pub fn atom_enclosures(entry: &atom_syndication::Entry) -> Vec<Enclosure> {
+    entry
+        .links()
+        .iter()
+        .filter(|link| link.rel() == "enclosure")
+        .map(|link| Enclosure::new(link.href(), link.mime_type(), link.length()))
+        .collect()
+}
+
+/// The rss and atom crates have separate but identical types for extension elements
+pub trait ExtensionElement: Sized {
+    fn attr(&self, name: &str) -> Option<&str>;
+    fn child_elements(&self, name: &str) -> &[Self];
+}
+
+impl ExtensionElement for rss::extension::Extension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs().get(name).map(String::as_str)
+    }
+
+    fn child_elements(&self, name: &str) -> &[Self] {
+        self.children().get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl ExtensionElement for atom_syndication::extension::Extension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs().get(name).map(String::as_str)
+    }
+
+    fn child_elements(&self, name: &str) -> &[Self] {
+        self.children().get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Get the `media:content` files and the first `media:thumbnail` from the Media RSS extension
+///
+/// Both can be set directly on the item, or nested in a `media:group`.
+pub fn media_rss<E: ExtensionElement>(
+    extensions: &HashMap<String, HashMap<String, Vec<E>>>,
+) -> (Vec<Enclosure>, Option<String>) {
+    let media = match extensions.get("media") {
+        Some(media) => media,
+        None => return (Vec::new(), None),
+    };
+    let elements = |name: &str| media.get(name).map_or(&[][..], Vec::as_slice);
+    let groups = elements("group");
+    let contents: Vec<&E> = elements("content")
+        .iter()
+        .chain(
+            groups
+                .iter()
+                .flat_map(|group| group.child_elements("content")),
+        )
+        .collect();
+
+    let content = contents
+        .iter()
+        .filter_map(|content| {
+            Some(Enclosure::new(
+                content.attr("url")?,
+                content.attr("type"),
+                content.attr("fileSize"),
+            ))
+        })
+        .collect();
+    let thumbnail = elements("thumbnail")
+        .iter()
+        .chain(
+            groups
+                .iter()
+                .flat_map(|group| group.child_elements("thumbnail")),
+        )
+        .chain(
+            contents
+                .iter()
+                .flat_map(|content| content.child_elements("thumbnail")),
+        )
+        .find_map(|thumbnail| thumbnail.attr("url"))
+        .map(String::from);
+    (content, thumbnail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let enclosure = Enclosure::new("https://example.com/a.mp3", Some("audio/mpeg"), None);
+        assert!(enclosure.matches(&["audio/mpeg".into()]));
+        assert!(enclosure.matches(&["video/*".into(), "Audio/*".into()]));
+        assert!(!enclosure.matches(&["audio/ogg".into()]));
+        assert!(!Enclosure::new("https://example.com/a", None, None).matches(&["audio/*".into()]));
+    }
+
+    #[test]
+    fn test_media_rss() {
+        let xml = r#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/">
+            <channel><title>feed</title><link>https://example.com</link><description>feed</description>
+            <item>
+                <title>episode</title>
+                <enclosure url="https://example.com/episode.mp3" length="1234" type="audio/mpeg"/>
+                <media:group>
+                    <media:content url="https://example.com/episode.mp4" type="video/mp4" fileSize="5678"/>
+                    <media:thumbnail url="https://example.com/episode.jpg"/>
+                </media:group>
+            </item>
+            </channel></rss>"#;
+        let channel = rss::Channel::read_from(xml.as_bytes()).unwrap();
+        let item = &channel.items()[0];
+        assert_eq!(
+            vec![Enclosure {
+                url: "https://example.com/episode.mp3".into(),
+                mime_type: Some("audio/mpeg".into()),
+                length: Some(1234),
+            }],
+            rss_enclosures(item)
+        );
+        let (content, thumbnail) = media_rss(item.extensions());
+        assert_eq!(
+            vec![Enclosure {
+                url: "https://example.com/episode.mp4".into(),
+                mime_type: Some("video/mp4".into()),
+                length: Some(5678),
+            }],
+            content
+        );
+        assert_eq!(Some("https://example.com/episode.jpg".into()), thumbnail);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::change::ChangeEvent;
+use crate::config::{MqttActionConfig, MqttConfig};
+use crate::error::ActionError;
+use rumqttc::{AsyncClient, ClientError, Event, MqttOptions, Packet, Transport};
+use rustls::crypto::ring;
+use rustls::{ClientConfig, RootCertStore};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+#[cfg(not(test))]
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+#[cfg(test)]
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// Connection to the mqtt broker, shared between all feeds
+#[derive(Clone)]
+pub struct MqttClient {
+    client: AsyncClient,
+    connected: Arc<AtomicBool>,
+}
+
+impl MqttClient {
+    /// Connect to the broker, the connection is driven by a background task that reconnects on failure
+    pub fn connect(config: &MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port());
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            options.set_credentials(username, password.expose());
+        }
+        if config.tls {
+            options.set_transport(tls_transport());
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(options, 16);
+        let connected = Arc::new(AtomicBool::new(false));
+        let host = config.host.clone();
+        let state = connected.clone();
+        tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!(host, "connected to mqtt broker");
+                        state.store(true, Ordering::Release);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        // polling the event loop again will reconnect
+                        warn!(host, error = %e, "mqtt connection error");
+                        state.store(false, Ordering::Release);
+                        sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+
+        MqttClient { client, connected }
+    }
+
+    /// Queue a message for the broker
+    ///
+    /// This never waits for the broker, messages are refused while the connection is down
+    /// or when the queue is full, so a slow or unreachable broker can't hold up polling.
+    /// A successful publish means the message was handed to a live connection.
+    pub fn publish(
+        &self,
+        config: &MqttActionConfig,
+        change: &ChangeEvent,
+    ) -> Result<(), ActionError> {
+        if !self.connected.load(Ordering::Acquire) {
+            return Err(ActionError::MqttDisconnected);
+        }
+        let payload = serde_json::to_vec(change).expect("changes can always be serialized");
+        debug!(topic = config.topic, "publishing mqtt message");
+        match self
+            .client
+            .try_publish(&config.topic, config.qos, config.retain, payload)
+        {
+            Ok(()) => Ok(()),
+            Err(ClientError::TryRequest(_)) => Err(ActionError::MqttQueueFull),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Tls using the bundled root certificates, like the http client
+///
+/// The crypto provider is set explicitly, as no process wide default provider is installed.
+fn tls_transport() -> Transport {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .expect("the ring provider supports the default protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Transport::tls_with_config(config.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change::ChangeKind;
+    use crate::key::Key;
+    use rumqttc::QoS;
+    use serde_json::Value;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::time::timeout;
+
+    async fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let packet_type = stream.read_u8().await.ok()?;
+        let mut length = 0;
+        for shift in (0..28).step_by(7) {
+            let byte = stream.read_u8().await.ok()?;
+            length |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.ok()?;
+        Some((packet_type >> 4, body))
+    }
+
+    /// Accept an mqtt connection and return the topic and payload of the first published message
+    async fn mqtt_session(listener: &TcpListener) -> Option<(String, Vec<u8>)> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        while let Some((packet_type, body)) = read_packet(&mut stream).await {
+            match packet_type {
+                // CONNECT
+                1 => stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap(),
+                // PUBLISH
+                3 => {
+                    let topic_length = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8(body[2..2 + topic_length].to_vec()).unwrap();
+                    return Some((topic, body[2 + topic_length..].to_vec()));
+                }
+                // PINGREQ
+                12 => stream.write_all(&[0xd0, 0x00]).await.unwrap(),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn config(port: u16) -> MqttConfig {
+        toml::from_str(&format!("host = \"127.0.0.1\"\nport = {}", port)).unwrap()
+    }
+
+    fn action() -> MqttActionConfig {
+        MqttActionConfig {
+            topic: "feeds/test".into(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+        }
+    }
+
+    fn change() -> ChangeEvent {
+        ChangeEvent {
+            kind: ChangeKind::Added,
+            source: "rss",
+            feed: "https://example.com/feed".into(),
+            items: Vec::new(),
+            old_key: None,
+            new_key: Key::default(),
+            tags: None,
+        }
+    }
+
+    async fn publish_when_connected(client: &MqttClient) {
+        for _ in 0..50 {
+            match client.publish(&action(), &change()) {
+                Ok(()) => return,
+                Err(ActionError::MqttDisconnected) => sleep(Duration::from_millis(50)).await,
+                Err(e) => panic!("failed to publish: {}", e),
+            }
+        }
+        panic!("never connected to the broker");
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = tokio::spawn(async move { mqtt_session(&listener).await });
+
+        let client = MqttClient::connect(&config(port));
+        publish_when_connected(&client).await;
+
+        let (topic, payload) = broker.await.unwrap().expect("no message published");
+        assert_eq!("feeds/test", topic);
+        let payload: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!("https://example.com/feed", payload["feed"]);
+        assert_eq!("added", payload["kind"]);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = tokio::spawn(async move {
+            // drop the first connection before acknowledging it
+            drop(listener.accept().await.unwrap());
+            mqtt_session(&listener).await
+        });
+
+        let client = MqttClient::connect(&config(port));
+        publish_when_connected(&client).await;
+
+        let (topic, _) = broker.await.unwrap().expect("no message published");
+        assert_eq!("feeds/test", topic);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_broker_does_not_block() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let client = MqttClient::connect(&config(port));
+        // more messages than fit in the request queue
+        timeout(Duration::from_secs(1), async {
+            for _ in 0..32 {
+                assert!(matches!(
+                    client.publish(&action(), &change()),
+                    Err(ActionError::MqttDisconnected)
+                ));
+                sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("publishing blocked on the broker");
+    }
+
+    #[test]
+    fn test_tls_transport() {
+        assert!(matches!(tls_transport(), Transport::Tls(_)));
+    }
+
+    #[test]
+    fn test_invalid_qos() {
+        assert!(toml::from_str::<MqttActionConfig>("topic = \"a\"\nqos = 3").is_err());
+        assert_eq!(
+            QoS::ExactlyOnce,
+            toml::from_str::<MqttActionConfig>("topic = \"a\"\nqos = 2")
+                .unwrap()
+                .qos
+        );
+    }
+}
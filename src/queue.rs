@@ -0,0 +1,51 @@
+use crate::change::{ChangeEvent, ChangeKind};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Triggers that are held back to be delivered later
+#[derive(Default)]
+pub struct TriggerQueue {
+    /// pending triggers by feed index, action index and change kind
+    pending: HashMap<(usize, usize, ChangeKind), PendingTrigger>,
+}
+
+struct PendingTrigger {
+    due: Instant,
+    change: ChangeEvent,
+}
+
+impl TriggerQueue {
+    /// Queue a trigger, if the action already has a pending trigger of the same kind the change is merged into it
+    pub fn push(&mut self, feed: usize, action: usize, change: ChangeEvent, due: Instant) {
+        let key = (feed, action, change.kind);
+        match self.pending.get_mut(&key) {
+            Some(pending) => pending.change.merge(change),
+            None => {
+                self.pending.insert(key, PendingTrigger { due, change });
+            }
+        }
+    }
+
+    /// Remove all triggers that are due, returning the feed index, action index and change
+    pub fn take_due(&mut self, now: Instant) -> Vec<(usize, usize, ChangeEvent)> {
+        let due: Vec<(usize, usize, ChangeKind)> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.due <= now)
+            .map(|(key, _)| *key)
+            .collect();
+        let mut triggers: Vec<_> = due
+            .into_iter()
+            .filter_map(|key| {
+                let pending = self.pending.remove(&key)?;
+                Some((key.0, key.1, pending.change))
+            })
+            .collect();
+        triggers.sort_by_key(|(feed, action, change)| (*feed, *action, change.kind));
+        triggers
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| pending.due).min()
+    }
+}
@@ -0,0 +1,265 @@
+use crate::action::{trigger, ActionContext, ActionRegistry};
+use crate::change::ChangeEvent;
+use crate::client::ClientPool;
+use crate::clock::Clock;
+use crate::config::{ActionConfig, ActionKind, Config, FeedConfig};
+use crate::error::ActionError;
+use crate::feed::FeedFetcher;
+use crate::history::{History, HistoryEntry, HistoryItem};
+use crate::mqtt::MqttClient;
+use crate::queue::TriggerQueue;
+use crate::secret::{redact, Redacted};
+use crate::source::SourceRegistry;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+/// Watch all feeds and trigger their actions, this runs until cancelled
+pub async fn run(
+    config: Config,
+    clients: ClientPool,
+    sources: SourceRegistry,
+    actions: ActionRegistry,
+    history: Option<History>,
+) -> Result<(), ActionError> {
+    Runner::new(config, clients, sources, actions, history)?
+        .run()
+        .await;
+    Ok(())
+}
+
+/// Maximum number of commands from `exec` actions running at the same time
+const MAX_RUNNING_COMMANDS: usize = 4;
+
+/// The main loop, checking the feeds and triggering their actions
+pub struct Runner {
+    config: Arc<Config>,
+    context: Arc<ActionContext>,
+    fetcher: FeedFetcher,
+    queue: TriggerQueue,
+    history: Option<Arc<Mutex<History>>>,
+    clock: Clock,
+    commands: Arc<Semaphore>,
+}
+
+impl Runner {
+    /// Fails if a feed uses a custom action that isn't registered
+    pub fn new(
+        config: Config,
+        clients: ClientPool,
+        sources: SourceRegistry,
+        actions: ActionRegistry,
+        history: Option<History>,
+    ) -> Result<Self, ActionError> {
+        let custom = config
+            .feed
+            .iter()
+            .flat_map(|feed| &feed.actions)
+            .filter_map(|action| match &action.kind {
+                ActionKind::Custom(custom) => Some(&custom.name),
+                _ => None,
+            });
+        for name in custom {
+            if actions.get(name).is_none() {
+                return Err(ActionError::UnknownAction(name.clone()));
+            }
+        }
+        let context = ActionContext {
+            client: clients.default_client().clone(),
+            mqtt: config.mqtt.as_ref().map(MqttClient::connect),
+            actions,
+        };
+        let fetcher = FeedFetcher::new(config.schedule(), clients, sources);
+        Ok(Runner {
+            config: Arc::new(config),
+            context: Arc::new(context),
+            fetcher,
+            queue: TriggerQueue::default(),
+            history: history.map(|history| Arc::new(Mutex::new(history))),
+            clock: Clock::default(),
+            commands: Arc::new(Semaphore::new(MAX_RUNNING_COMMANDS)),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.fetcher = self.fetcher.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            let wakeup = self.tick().await;
+            sleep(wakeup.saturating_duration_since(self.clock.now())).await;
+        }
+    }
+
+    /// Check all feeds that are due and trigger the actions for new or edited items
+    ///
+    /// Returns the time at which the next tick should run.
+    pub async fn tick(&mut self) -> Instant {
+        let config = self.config.clone();
+        for (feed_index, feed) in config.feed.iter().enumerate() {
+            for change in self.fetcher.check_feed_updated(feed).await {
+                for (action_index, action) in feed.actions.iter().enumerate() {
+                    if !action.trigger_on.contains(&change.kind) {
+                        continue;
+                    }
+                    match delay_until(action, &self.clock) {
+                        Some(due) => {
+                            info!(feed = feed.feed, action = action.kind(), "delaying trigger");
+                            self.queue
+                                .push(feed_index, action_index, change.clone(), due);
+                        }
+                        None => self.start_action(feed_index, action_index, &change).await,
+                    }
+                }
+            }
+        }
+
+        for (feed_index, action_index, change) in self.queue.take_due(self.clock.now()) {
+            let action = &config.feed[feed_index].actions[action_index];
+            match action.quiet.filter(|quiet| quiet.is_active(&self.clock)) {
+                Some(quiet) => self.queue.push(
+                    feed_index,
+                    action_index,
+                    change,
+                    quiet.next_end(&self.clock),
+                ),
+                None => self.start_action(feed_index, action_index, &change).await,
+            }
+        }
+
+        let now = self.clock.now();
+        [self.fetcher.next_planned_fetch(), self.queue.next_due()]
+            .iter()
+            .flatten()
+            .copied()
+            .fold(now + self.config.interval(), Instant::min)
+            .max(now + Duration::from_secs(1))
+    }
+
+    /// Run an action, commands are run in the background as they can take minutes to complete
+    async fn start_action(&self, feed_index: usize, action_index: usize, change: &ChangeEvent) {
+        let feed = &self.config.feed[feed_index];
+        let action = &feed.actions[action_index];
+        if !matches!(action.kind, ActionKind::Exec(_)) {
+            let history = self.history.as_deref();
+            run_action(&self.context, history, &self.clock, feed, action, change).await;
+            return;
+        }
+
+        let config = self.config.clone();
+        let context = self.context.clone();
+        let history = self.history.clone();
+        let clock = self.clock.clone();
+        let commands = self.commands.clone();
+        let change = change.clone();
+        tokio::spawn(async move {
+            let _permit = commands.acquire_owned().await;
+            let feed = &config.feed[feed_index];
+            let action = &feed.actions[action_index];
+            run_action(&context, history.as_deref(), &clock, feed, action, &change).await
+        });
+    }
+}
+
+/// Get the time until which a new trigger for the action should be held back
+fn delay_until(action: &ActionConfig, clock: &Clock) -> Option<Instant> {
+    let debounce = action
+        .debounce
+        .map(|debounce| clock.now() + Duration::from_secs(debounce));
+    let quiet = action
+        .quiet
+        .filter(|quiet| quiet.is_active(clock))
+        .map(|quiet| quiet.next_end(clock));
+    debounce.max(quiet)
+}
+
+async fn run_action(
+    context: &ActionContext,
+    history: Option<&Mutex<History>>,
+    clock: &Clock,
+    feed: &FeedConfig,
+    action: &ActionConfig,
+    change: &ChangeEvent,
+) {
+    let time = clock.now_utc();
+    let selected = action.items.select(&change.items);
+    let started = Instant::now();
+    let result = trigger(context, action, change).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let hook = Redacted(action.kind.target());
+    let (request, http_status, error) = match result {
+        Ok(delivery) => (
+            delivery.as_ref().map(|delivery| delivery.request.clone()),
+            delivery.map(|delivery| delivery.status),
+            None,
+        ),
+        Err(e) => (
+            e.url().map(|url| redact(url.as_str())),
+            e.status().map(|status| status.as_u16()),
+            Some(e),
+        ),
+    };
+    // both events have the same fields, so they are easy to query
+    match &error {
+        None => info!(
+            feed = feed.feed,
+            action = action.kind(),
+            hook = %hook,
+            source = change.source,
+            change = change.kind.name(),
+            new_key = %change.new_key,
+            items = selected.len(),
+            status = "success",
+            duration_ms,
+            http_status = http_status.unwrap_or(0),
+            error_kind = "",
+            error = "",
+            "action triggered"
+        ),
+        Some(e) => error!(
+            feed = feed.feed,
+            action = action.kind(),
+            hook = %hook,
+            source = change.source,
+            change = change.kind.name(),
+            new_key = %change.new_key,
+            items = selected.len(),
+            status = "failed",
+            duration_ms,
+            http_status = http_status.unwrap_or(0),
+            error_kind = e.kind(),
+            error = %Redacted(e),
+            "action triggered"
+        ),
+    }
+
+    if let Some(history) = history {
+        let entry = HistoryEntry {
+            time,
+            feed: feed.feed.clone(),
+            action: action.kind().into(),
+            hook: hook.to_string(),
+            source: change.source.into(),
+            change: change.kind,
+            old_key: change.old_key.map(|key| key.to_string()),
+            new_key: change.new_key.to_string(),
+            tags: change.tags.clone(),
+            items: selected.iter().map(HistoryItem::from).collect(),
+            status: if error.is_some() { "failed" } else { "success" }.into(),
+            request,
+            http_status,
+            duration_ms,
+            error_kind: error.as_ref().map(|e| e.kind().into()),
+            error: error.as_ref().map(|e| Redacted(e).to_string()),
+        };
+        let mut history = history.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = history.append(&entry) {
+            error!(error = %e, "failed to write trigger history");
+        }
+    }
+}
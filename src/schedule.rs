@@ -0,0 +1,52 @@
+use crate::clock::Clock;
+use chrono::{Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use std::time::Instant;
+
+/// A daily window in local time, the end can be before the start for windows that span midnight
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct QuietWindow {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: NaiveTime,
+}
+
+impl QuietWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    pub fn is_active(&self, clock: &Clock) -> bool {
+        self.contains(clock.now_local().time())
+    }
+
+    /// The first time the window ends after now
+    pub fn next_end(&self, clock: &Clock) -> Instant {
+        let now = clock.now_local();
+        let mut date = now.date_naive();
+        if now.time() >= self.end {
+            date = date.succ_opt().unwrap_or(date);
+        }
+        let end = Local
+            .from_local_datetime(&date.and_time(self.end))
+            .earliest()
+            // the end falls in a dst gap, an hour later will exist
+            .unwrap_or_else(|| now + ChronoDuration::hours(1));
+        clock.instant_at(end)
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&raw, "%H:%M")
+        .map_err(|e| D::Error::custom(format!("invalid time {}, expected HH:MM: {}", raw, e)))
+}
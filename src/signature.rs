@@ -0,0 +1,16 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sign a request body github-style, when a timestamp is provided the signed payload is `{timestamp}.{body}`
+pub fn sign(secret: &str, timestamp: Option<i64>, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    if let Some(timestamp) = timestamp {
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+    }
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
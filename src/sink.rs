@@ -0,0 +1,187 @@
+use crate::action::{api_url, send, Delivery};
+use crate::config::{DiscordConfig, GotifyConfig, MatrixConfig, NtfyConfig, SlackConfig};
+use crate::error::ActionError;
+use crate::item::FeedItem;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use time::OffsetDateTime;
+
+/// discord limits embed descriptions to 4096 characters, but we don't want walls of text anyway
+const MAX_SUMMARY_LENGTH: usize = 500;
+
+pub async fn discord(
+    client: &Client,
+    config: &DiscordConfig,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let mut embed = json!({
+        "title": item.display_title(),
+    });
+    if let Some(link) = &item.link {
+        embed["url"] = link.as_str().into();
+    }
+    if let Some(summary) = item.plain_summary(MAX_SUMMARY_LENGTH) {
+        embed["description"] = summary.into();
+    }
+    let mut body = json!({ "embeds": [embed] });
+    if let Some(username) = &config.username {
+        body["username"] = username.as_str().into();
+    }
+    send(client.post(config.webhook.expose()).json(&body)).await
+}
+
+pub async fn slack(
+    client: &Client,
+    config: &SlackConfig,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let title = slack_escape(item.display_title());
+    let mut text = match &item.link {
+        Some(link) => format!("<{}|{}>", slack_escape(link), title),
+        None => title,
+    };
+    if let Some(summary) = item.plain_summary(MAX_SUMMARY_LENGTH) {
+        text.push('\n');
+        text.push_str(&slack_escape(&summary));
+    }
+    send(
+        client
+            .post(config.webhook.expose())
+            .json(&json!({ "text": text })),
+    )
+    .await
+}
+
+pub async fn matrix(
+    client: &Client,
+    config: &MatrixConfig,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    static TRANSACTION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let title = item.display_title();
+    let summary = item.plain_summary(MAX_SUMMARY_LENGTH);
+    let mut body = title.to_string();
+    let mut formatted = match &item.link {
+        Some(link) => {
+            body.push('\n');
+            body.push_str(link);
+            format!(
+                "<a href=\"{}\">{}</a>",
+                html_escape(link),
+                html_escape(title)
+            )
+        }
+        None => html_escape(title),
+    };
+    if let Some(summary) = summary {
+        body.push('\n');
+        body.push_str(&summary);
+        formatted.push_str("<br>");
+        formatted.push_str(&html_escape(&summary));
+    }
+
+    let transaction = format!(
+        "rss-webhook-trigger-{}-{}",
+        OffsetDateTime::now_utc().unix_timestamp_nanos(),
+        TRANSACTION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let url = api_url(
+        &config.homeserver,
+        &[
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            &config.room,
+            "send",
+            "m.room.message",
+            &transaction,
+        ],
+    )?;
+
+    send(
+        client
+            .put(url)
+            .bearer_auth(config.access_token.expose())
+            .json(&json!({
+                "msgtype": if config.notice { "m.notice" } else { "m.text" },
+                "body": body,
+                "format": "org.matrix.custom.html",
+                "formatted_body": formatted,
+            })),
+    )
+    .await
+}
+
+pub async fn ntfy(
+    client: &Client,
+    config: &NtfyConfig,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let mut body = json!({
+        "topic": config.topic,
+        "title": item.display_title(),
+        "message": item
+            .plain_summary(MAX_SUMMARY_LENGTH)
+            .or_else(|| item.link.clone())
+            .unwrap_or_default(),
+    });
+    if let Some(link) = &item.link {
+        body["click"] = link.as_str().into();
+    }
+    if let Some(priority) = config.priority {
+        body["priority"] = priority.into();
+    }
+    if !config.tags.is_empty() {
+        body["tags"] = config.tags.clone().into();
+    }
+    let mut req = client.post(&config.server).json(&body);
+    if let Some(token) = &config.token {
+        req = req.bearer_auth(token.expose());
+    }
+    send(req).await
+}
+
+pub async fn gotify(
+    client: &Client,
+    config: &GotifyConfig,
+    item: &FeedItem,
+) -> Result<Delivery, ActionError> {
+    let mut message = item.plain_summary(MAX_SUMMARY_LENGTH).unwrap_or_default();
+    let mut body = json!({
+        "title": item.display_title(),
+    });
+    if let Some(link) = &item.link {
+        if !message.is_empty() {
+            message.push('\n');
+        }
+        message.push_str(link);
+        body["extras"] = json!({
+            "client::notification": { "click": { "url": link } }
+        });
+    }
+    body["message"] = message.into();
+    if let Some(priority) = config.priority {
+        body["priority"] = priority.into();
+    }
+    let url = format!("{}/message", config.server.trim_end_matches('/'));
+    send(
+        client
+            .post(url)
+            .header("X-Gotify-Key", config.token.expose())
+            .json(&body),
+    )
+    .await
+}
+
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn html_escape(text: &str) -> String {
+    slack_escape(text).replace('"', "&quot;")
+}
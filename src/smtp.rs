@@ -0,0 +1,200 @@
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::error::ActionError;
+use crate::item::FeedItem;
+use crate::template::render;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::debug;
+
+pub async fn send(config: &SmtpConfig, feed: &str, items: &[FeedItem]) -> Result<(), ActionError> {
+    let transport = transport(config)?;
+    let from: Mailbox = config.from.parse()?;
+    let to = config
+        .to
+        .iter()
+        .map(|to| to.parse())
+        .collect::<Result<Vec<Mailbox>, _>>()?;
+
+    let mails: Vec<(String, String)> = match items {
+        [first, _, ..] if config.batch => {
+            let subject = config
+                .batch_subject
+                .replace("{count}", &items.len().to_string());
+            let body = items
+                .iter()
+                .map(|item| render(&config.body, feed, item))
+                .collect::<Vec<_>>()
+                .join("\n\n---\n\n");
+            vec![(render(&subject, feed, first), body)]
+        }
+        _ => items
+            .iter()
+            .map(|item| {
+                (
+                    render(&config.subject, feed, item),
+                    render(&config.body, feed, item),
+                )
+            })
+            .collect(),
+    };
+
+    for (subject, body) in mails {
+        let mut builder = Message::builder().from(from.clone()).subject(subject);
+        for to in &to {
+            builder = builder.to(to.clone());
+        }
+        let message = builder.body(body)?;
+        debug!(host = config.host, "sending email");
+        transport.send(message).await?;
+    }
+    Ok(())
+}
+
+fn transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, ActionError> {
+    let mut builder = match config.tls {
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+    };
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            password.expose().to_string(),
+        ));
+    }
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActionKind, Config};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Accept a number of smtp sessions and return the received message data
+    async fn smtp_stand_in(listener: TcpListener, sessions: usize) -> Vec<String> {
+        let mut messages = Vec::new();
+        for _ in 0..sessions {
+            smtp_session(&listener, &mut messages).await;
+        }
+        messages
+    }
+
+    async fn smtp_session(listener: &TcpListener, messages: &mut Vec<String>) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut data: Option<String> = None;
+        write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if let Some(message) = data.as_mut() {
+                if line == "." {
+                    messages.push(data.take().unwrap());
+                    write.write_all(b"250 OK\r\n").await.unwrap();
+                } else {
+                    message.push_str(&line);
+                    message.push('\n');
+                }
+                continue;
+            }
+            let command = line.to_ascii_uppercase();
+            if command.starts_with("EHLO") || command.starts_with("HELO") {
+                write.write_all(b"250 localhost\r\n").await.unwrap();
+            } else if command.starts_with("DATA") {
+                data = Some(String::new());
+                write.write_all(b"354 go ahead\r\n").await.unwrap();
+            } else if command.starts_with("QUIT") {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                write.write_all(b"250 OK\r\n").await.unwrap();
+            }
+        }
+    }
+
+    fn config(port: u16, batch: bool) -> SmtpConfig {
+        let config = format!(
+            r#"
+            type = "smtp"
+            host = "127.0.0.1"
+            port = {port}
+            tls = "none"
+            from = "trigger@example.com"
+            to = ["someone@example.com"]
+            batch = {batch}
+            "#
+        );
+        match toml::from_str(&config).unwrap() {
+            ActionKind::Smtp(config) => config,
+            _ => unreachable!(),
+        }
+    }
+
+    fn item(title: &str) -> FeedItem {
+        FeedItem {
+            title: Some(title.into()),
+            link: Some(format!("https://example.com/{}", title)),
+            ..FeedItem::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_batch() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(smtp_stand_in(listener, 1));
+
+        let items = [item("first"), item("second")];
+        send(&config(port, true), "https://example.com/feed", &items)
+            .await
+            .unwrap();
+
+        let messages = server.await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("Subject: 2 new items in https://example.com/feed"));
+        assert!(messages[0].contains("https://example.com/first"));
+        assert!(messages[0].contains("https://example.com/second"));
+    }
+
+    #[tokio::test]
+    async fn test_send_single() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(smtp_stand_in(listener, 2));
+
+        let items = [item("first"), item("second")];
+        send(&config(port, false), "https://example.com/feed", &items)
+            .await
+            .unwrap();
+
+        let messages = server.await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("Subject: first"));
+        assert!(messages[1].contains("Subject: second"));
+    }
+
+    #[test]
+    fn test_password_without_username() {
+        let config = r#"
+            [[feed]]
+            feed = "https://example.com/feed.xml"
+
+            [[feed.action]]
+            type = "smtp"
+            host = "127.0.0.1"
+            password = "secret"
+            from = "trigger@example.com"
+            to = ["someone@example.com"]
+            "#;
+        assert!(Config::from_toml(config).is_err());
+        assert!(Config::from_toml(
+            &config.replace("password", "username = \"trigger\"\n            password")
+        )
+        .is_ok());
+    }
+}
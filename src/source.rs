@@ -0,0 +1,276 @@
+use crate::config::{FeedConfig, ItemKeyConfig, KeyField};
+use crate::error::{FetchError, FetchFeedError, HubError, ParseFeedError};
+use crate::fetcher::{CacheHeaders, FetchHints, FetchResponse};
+use crate::hub::{self, HubTag};
+use crate::item::{FeedItem, FeedSnapshot};
+use crate::key::{Key, KeyBuilder};
+use crate::FETCHER_USER_AGENT;
+use async_trait::async_trait;
+use reqwest::header::{HeaderValue, USER_AGENT};
+use reqwest::{Client, Response};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::future::ready;
+use std::str::FromStr;
+use std::sync::Arc;
+use syndication::Feed;
+use tracing::{instrument, warn};
+
+/// A source of feed items, such as an rss feed or a docker hub repository
+#[async_trait]
+pub trait FeedSource: Send + Sync {
+    /// Name of the source type, used in logs
+    fn name(&self) -> &'static str;
+
+    /// Fetch the current items of the feed, using the cache headers from the previous fetch
+    async fn fetch(
+        &self,
+        client: &Client,
+        feed: &FeedConfig,
+        cache_headers: &CacheHeaders,
+    ) -> FetchResponse<FeedSnapshot, FetchError>;
+}
+
+/// Maps the scheme of a feed url to the source that handles it
+#[derive(Clone)]
+pub struct SourceRegistry {
+    sources: HashMap<String, Arc<dyn FeedSource>>,
+}
+
+impl Default for SourceRegistry {
+    /// Registry with the builtin rss/atom and docker hub sources
+    fn default() -> Self {
+        let mut registry = SourceRegistry::empty();
+        registry.register("http", RssSource);
+        registry.register("https", RssSource);
+        registry.register("docker-hub", DockerHubSource);
+        registry
+    }
+}
+
+impl SourceRegistry {
+    pub fn empty() -> Self {
+        SourceRegistry {
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Register a source for a url scheme, replacing any existing source for the scheme
+    pub fn register<S: FeedSource + 'static>(&mut self, scheme: &str, source: S) {
+        self.sources.insert(scheme.into(), Arc::new(source));
+    }
+
+    /// Find the source for a feed url
+    pub fn get(&self, url: &str) -> Option<&dyn FeedSource> {
+        let (scheme, _) = url.split_once("://")?;
+        self.sources.get(scheme).map(Arc::as_ref)
+    }
+}
+
+/// Rss or atom feed fetched over http
+pub struct RssSource;
+
+#[async_trait]
+impl FeedSource for RssSource {
+    fn name(&self) -> &'static str {
+        "feed"
+    }
+
+    #[instrument(skip_all, fields(url = feed.feed))]
+    async fn fetch(
+        &self,
+        client: &Client,
+        feed: &FeedConfig,
+        cache_headers: &CacheHeaders,
+    ) -> FetchResponse<FeedSnapshot, FetchError> {
+        let response = feed
+            .auth
+            .apply(client.get(&feed.feed))
+            .headers(cache_headers.headers())
+            .header(USER_AGENT, HeaderValue::from_static(FETCHER_USER_AGENT))
+            .send()
+            .await;
+
+        let plan_result = FetchResponse::from_result(response);
+        plan_result
+            .map_err(FetchFeedError::Network)
+            .check_status_code(FetchFeedError::ClientError, FetchFeedError::ServerError)
+            .map(|response| parse_rss_response(response, feed))
+            .await
+            .flatten()
+            .map_err(FetchError::Feed)
+    }
+}
+
+/// Tags of a docker hub repository, configured as `docker-hub://user/repo`
+pub struct DockerHubSource;
+
+#[async_trait]
+impl FeedSource for DockerHubSource {
+    fn name(&self) -> &'static str {
+        "docker_hub"
+    }
+
+    #[instrument(skip_all, fields(url = feed.feed))]
+    async fn fetch(
+        &self,
+        client: &Client,
+        feed: &FeedConfig,
+        cache_headers: &CacheHeaders,
+    ) -> FetchResponse<FeedSnapshot, FetchError> {
+        let hub = feed.feed.trim_start_matches("docker-hub://");
+        if let Some((user, repo)) = hub.split_once('/') {
+            hub::tags(client, user, repo, &feed.auth, cache_headers)
+                .await
+                .map(|mut tags| {
+                    let key = tags
+                        .iter()
+                        .fold(Key::builder("docker_hub"), |key, tag| {
+                            hub_tag_fields(key, tag)
+                        })
+                        .finish();
+                    tags.sort_by_key(|tag| Reverse(tag.last_updated));
+                    let items: Vec<FeedItem> = tags
+                        .iter()
+                        .map(|tag| {
+                            let key = hub_tag_fields(Key::builder("docker_hub_tag"), tag).finish();
+                            FeedItem::from_hub_tag(key, user, repo, tag)
+                        })
+                        .collect();
+                    let tags = tags
+                        .iter()
+                        .zip(&items)
+                        .map(|(tag, item)| (tag.name.clone(), item.key))
+                        .collect();
+                    ready(FeedSnapshot {
+                        key,
+                        items,
+                        hints: FetchHints::default(),
+                        tags: Some(tags),
+                    })
+                })
+                .await
+                .map_err(FetchError::Hub)
+        } else {
+            FetchResponse::Error {
+                error: HubError::InvalidFormat.into(),
+                headers: CacheHeaders::default(),
+            }
+        }
+    }
+}
+
+async fn parse_rss_response(
+    response: Response,
+    feed: &FeedConfig,
+) -> Result<FeedSnapshot, FetchFeedError> {
+    let key_config = feed.item_key.as_ref();
+    let content = response.text().await?;
+    let channel = Feed::from_str(&content).map_err(ParseFeedError::Parse)?;
+
+    let mut hints = FetchHints::default();
+    let items: Vec<FeedItem> = match channel {
+        Feed::RSS(channel) => {
+            hints = FetchHints::from_rss(&channel);
+            let mut items = Vec::new();
+            for (index, item) in channel.items().iter().enumerate() {
+                match rss_item_key(item, key_config) {
+                    Some(key) => items.push(FeedItem::from_rss(key, item)),
+                    // the newest item identifies the state of the feed, so it can't be skipped
+                    None if index == 0 => return Err(ParseFeedError::MissingKey.into()),
+                    None => warn!(
+                        feed = feed.feed,
+                        title = item.title(),
+                        "skipping feed item without the fields needed to identify it"
+                    ),
+                }
+            }
+            items
+        }
+        Feed::Atom(channel) => {
+            let mut items = Vec::new();
+            for (index, entry) in channel.entries().iter().enumerate() {
+                match atom_entry_key(entry, key_config) {
+                    Some(key) => items.push(FeedItem::from_atom(key, entry)),
+                    None if index == 0 => return Err(ParseFeedError::MissingKey.into()),
+                    None => warn!(
+                        feed = feed.feed,
+                        title = entry.title(),
+                        "skipping feed item without the fields needed to identify it"
+                    ),
+                }
+            }
+            items
+        }
+    };
+
+    let items: Vec<FeedItem> = if feed.enclosure_types.is_empty() {
+        items
+    } else {
+        items
+            .into_iter()
+            .filter(|item| item.has_enclosure_type(&feed.enclosure_types))
+            .collect()
+    };
+
+    // an empty feed is a valid state, new items will be picked up once they are published
+    let key = items
+        .first()
+        .map_or_else(FeedSnapshot::empty_key, |item| item.key);
+    Ok(FeedSnapshot {
+        key,
+        items,
+        hints,
+        tags: None,
+    })
+}
+
+fn rss_item_key(item: &rss::Item, config: Option<&ItemKeyConfig>) -> Option<Key> {
+    if let Some(config) = config {
+        return config.key("rss_item", |field| match field {
+            KeyField::Guid => item.guid().map(|guid| guid.value()),
+            KeyField::Title => item.title(),
+            KeyField::Link => item.link(),
+            KeyField::PubDate => item.pub_date(),
+            KeyField::Enclosure => item.enclosure().map(|enclosure| enclosure.url()),
+        });
+    }
+    let key = Key::builder("rss_item");
+    let key = if let Some(guid) = item.guid() {
+        key.field("guid").field(&guid.value)
+    } else if let Some(date) = item.pub_date() {
+        key.field("pub_date").field(date)
+    } else if let Some(link) = item.link() {
+        key.field("link").field(link)
+    } else {
+        return None;
+    };
+    Some(key.finish())
+}
+
+fn atom_entry_key(entry: &atom_syndication::Entry, config: Option<&ItemKeyConfig>) -> Option<Key> {
+    match config {
+        Some(config) => config.key("atom_entry", |field| match field {
+            KeyField::Guid => Some(entry.id()),
+            KeyField::Title => Some(entry.title()),
+            KeyField::Link => entry
+                .links()
+                .iter()
+                .find(|link| link.rel() == "alternate")
+                .or_else(|| entry.links().first())
+                .map(|link| link.href()),
+            KeyField::PubDate => entry.published(),
+            KeyField::Enclosure => entry
+                .links()
+                .iter()
+                .find(|link| link.rel() == "enclosure")
+                .map(|link| link.href()),
+        }),
+        None => Some(Key::builder("atom_entry").field(entry.id()).finish()),
+    }
+}
+
+fn hub_tag_fields(key: KeyBuilder, tag: &HubTag) -> KeyBuilder {
+    key.field(tag.id.to_be_bytes())
+        .field(tag.last_updated.unix_timestamp_nanos().to_be_bytes())
+}
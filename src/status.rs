@@ -0,0 +1,296 @@
+use crate::config::{HistoryConfig, StatusConfig};
+use crate::error::StatusError;
+use crate::history;
+use reqwest::StatusCode;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::spawn_blocking;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use url::Url;
+
+const DEFAULT_LIMIT: usize = 20;
+/// upper limit for the request line and headers together
+const MAX_HEAD_SIZE: u64 = 8 * 1024;
+/// time a client gets to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimal http api for querying the trigger history
+///
+/// - `GET /history?feed=<url>&limit=<count>`: the latest history entries as json, newest first
+///
+/// The api has no authentication, so it should only listen on a loopback address, or be put behind
+/// a reverse proxy that handles access.
+pub struct StatusServer {
+    listener: TcpListener,
+    history: Option<PathBuf>,
+    read_timeout: Duration,
+}
+
+impl StatusServer {
+    pub async fn bind(
+        config: &StatusConfig,
+        history: Option<&HistoryConfig>,
+    ) -> Result<Self, StatusError> {
+        let listener =
+            TcpListener::bind(config.listen)
+                .await
+                .map_err(|error| StatusError::Bind {
+                    error,
+                    address: config.listen,
+                })?;
+        if !config.listen.ip().is_loopback() {
+            warn!(address = %config.listen, "status api is reachable from other hosts without authentication");
+        }
+        Ok(StatusServer {
+            listener,
+            history: history.map(|history| history.path.clone()),
+            read_timeout: READ_TIMEOUT,
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Handle requests, this runs until cancelled
+    pub async fn serve(self) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle(stream, self.history.clone(), self.read_timeout));
+                }
+                Err(e) => warn!(error = %e, "failed to accept status connection"),
+            }
+        }
+    }
+}
+
+async fn handle(stream: TcpStream, history: Option<PathBuf>, read_timeout: Duration) {
+    let (read, mut write) = stream.into_split();
+    let (status, body) = match timeout(read_timeout, read_request_line(read)).await {
+        Ok(Ok(request_line)) => respond(&request_line, history).await,
+        Ok(Err(response)) => response,
+        Err(_) => error(StatusCode::REQUEST_TIMEOUT, "request timeout"),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    if let Err(e) = write.write_all(response.as_bytes()).await {
+        debug!(error = %e, "failed to send status response");
+    }
+}
+
+/// Read the request line, requests don't have a body so the headers are only skipped
+async fn read_request_line(read: OwnedReadHalf) -> Result<String, (StatusCode, String)> {
+    let mut reader = BufReader::new(read.take(MAX_HEAD_SIZE));
+    let mut request_line = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(_) if line.ends_with('\n') => {}
+            // the limit is only reached before the end of the line if the request is too large
+            Ok(_) if reader.get_ref().limit() == 0 => {
+                return Err(error(
+                    StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                    "request too large",
+                ))
+            }
+            _ => return Err(error(StatusCode::BAD_REQUEST, "invalid request")),
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        match request_line {
+            None => request_line = Some(line.to_string()),
+            Some(request_line) if line.is_empty() => return Ok(request_line),
+            Some(_) => {}
+        }
+    }
+}
+
+async fn respond(request_line: &str, history: Option<PathBuf>) -> (StatusCode, String) {
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap_or_default();
+    let url = match Url::parse("http://localhost")
+        .and_then(|base| base.join(parts.next().unwrap_or_default()))
+    {
+        Ok(url) => url,
+        Err(_) => return error(StatusCode::BAD_REQUEST, "invalid request"),
+    };
+    if method != "GET" {
+        return error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+    }
+
+    match (url.path(), history) {
+        ("/history", Some(path)) => {
+            let mut feed = None;
+            let mut limit = DEFAULT_LIMIT;
+            for (name, value) in url.query_pairs() {
+                match name.as_ref() {
+                    "feed" => feed = Some(value.into_owned()),
+                    "limit" => match value.parse() {
+                        Ok(value) => limit = value,
+                        Err(_) => return error(StatusCode::BAD_REQUEST, "invalid limit"),
+                    },
+                    _ => {}
+                }
+            }
+            // the whole file is read, which shouldn't block other tasks
+            let entries = spawn_blocking(move || history::query(&path, feed.as_deref(), limit));
+            match entries.await {
+                Ok(Ok(entries)) => (
+                    StatusCode::OK,
+                    serde_json::to_string(&entries)
+                        .expect("history entries can always be serialized"),
+                ),
+                Ok(Err(e)) => {
+                    warn!(error = %e, "failed to read trigger history");
+                    error(StatusCode::INTERNAL_SERVER_ERROR, "failed to read history")
+                }
+                Err(e) => {
+                    warn!(error = %e, "failed to read trigger history");
+                    error(StatusCode::INTERNAL_SERVER_ERROR, "failed to read history")
+                }
+            }
+        }
+        ("/history", None) => error(StatusCode::NOT_FOUND, "no history configured"),
+        _ => error(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> (StatusCode, String) {
+    (status, json!({ "error": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::path::Path;
+
+    fn entry(feed: &str, title: &str) -> String {
+        json!({
+            "time": "2024-01-01T00:00:00Z",
+            "feed": feed,
+            "action": "webhook",
+            "hook": "https://example.com/hook",
+            "items": [{ "key": "v1:00", "title": title }],
+            "status": "success",
+            "duration_ms": 10,
+        })
+        .to_string()
+    }
+
+    /// Start a server for the history file, returning its address
+    async fn start(history: Option<&Path>, read_timeout: Duration) -> SocketAddr {
+        let history: Option<HistoryConfig> =
+            history.map(|path| toml::from_str(&format!("path = {:?}", path)).unwrap());
+        let config = StatusConfig {
+            listen: "127.0.0.1:0".parse().unwrap(),
+        };
+        let mut server = StatusServer::bind(&config, history.as_ref()).await.unwrap();
+        server.read_timeout = read_timeout;
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+        addr
+    }
+
+    /// Send raw bytes, returning the status code of the response
+    async fn send_raw(addr: SocketAddr, request: &[u8]) -> u16 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        // the server might respond before everything is sent
+        stream.write_all(request).await.ok();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.ok();
+        response
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let path = std::env::temp_dir().join(format!(
+            "rss-webhook-trigger-status-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            [entry("a", "v1"), entry("b", "v1"), entry("a", "v2")].join("\n"),
+        )
+        .unwrap();
+        let addr = start(Some(&path), READ_TIMEOUT).await;
+        let base = format!("http://{}", addr);
+
+        let client = reqwest::Client::new();
+        let get = |query: &str| client.get(format!("{}/history{}", base, query)).send();
+        let entries: Value = get("?feed=a&limit=1").await.unwrap().json().await.unwrap();
+        let entries = entries.as_array().unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!("v2", entries[0]["items"][0]["title"]);
+        assert_eq!("v1:00", entries[0]["items"][0]["key"]);
+
+        let entries: Value = get("").await.unwrap().json().await.unwrap();
+        assert_eq!(3, entries.as_array().unwrap().len());
+        let entries: Value = get("?limit=2").await.unwrap().json().await.unwrap();
+        assert_eq!(2, entries.as_array().unwrap().len());
+        let response = get("?limit=-1").await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = client.get(format!("{}/feeds", base)).send().await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let response = client
+            .post(format!("{}/history", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, response.status());
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_no_history() {
+        let addr = start(None, READ_TIMEOUT).await;
+        let response = reqwest::get(format!("http://{}/history", addr))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let body: Value = response.json().await.unwrap();
+        assert_eq!("no history configured", body["error"]);
+    }
+
+    /// Request filled up to the size limit, so nothing is left unread that would reset the connection
+    fn oversized(start: &str) -> Vec<u8> {
+        let mut request = start.as_bytes().to_vec();
+        request.resize(MAX_HEAD_SIZE as usize, b'a');
+        request
+    }
+
+    #[tokio::test]
+    async fn test_request_too_large() {
+        let addr = start(None, READ_TIMEOUT).await;
+        assert_eq!(431, send_raw(addr, &oversized("GET /")).await);
+        let headers = "X-Header: value\r\n".repeat(400);
+        let request = oversized(&format!("GET /history HTTP/1.1\r\n{}X-Last: ", headers));
+        assert_eq!(431, send_raw(addr, &request).await);
+        // smaller requests are still handled
+        assert_eq!(404, send_raw(addr, b"GET /history HTTP/1.1\r\n\r\n").await);
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let addr = start(None, Duration::from_millis(100)).await;
+        // the headers are never finished
+        assert_eq!(408, send_raw(addr, b"GET /history HTTP/1.1\r\n").await);
+    }
+}
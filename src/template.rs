@@ -0,0 +1,69 @@
+use crate::change::ChangeEvent;
+use crate::item::FeedItem;
+use serde_json::Value;
+
+/// Render a template by replacing the `{feed}`, `{title}`, `{link}`, `{summary}` and `{enclosure}` placeholders
+///
+/// Unknown placeholders are left as is.
+pub fn render(template: &str, feed: &str, item: &FeedItem) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let end = match placeholder.find('}') {
+            Some(end) => end,
+            None => {
+                rest = placeholder;
+                break;
+            }
+        };
+        let value = match &placeholder[1..end] {
+            "feed" => Some(feed),
+            "title" => Some(item.display_title()),
+            "link" => Some(item.link.as_deref().unwrap_or_default()),
+            "summary" => Some(item.summary.as_deref().unwrap_or_default()),
+            "enclosure" => Some(item.enclosure_url().unwrap_or_default()),
+            _ => None,
+        };
+        match value {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&placeholder[..=end]),
+        }
+        rest = &placeholder[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Render all strings in a json value
+///
+/// A string that is exactly `{items}` is replaced by the list of items and `{change}` by the full change,
+/// other placeholders are filled in using the newest item.
+pub fn render_json(value: &Value, change: &ChangeEvent) -> Value {
+    match value {
+        Value::String(template) if template == "{items}" => {
+            serde_json::to_value(&change.items).expect("items can always be serialized")
+        }
+        Value::String(template) if template == "{change}" => {
+            serde_json::to_value(change).expect("changes can always be serialized")
+        }
+        Value::String(template) => {
+            let item = change.items.first().cloned().unwrap_or_default();
+            Value::String(render(template, &change.feed, &item))
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| render_json(value, change))
+                .collect(),
+        ),
+        Value::Object(values) => Value::Object(
+            values
+                .iter()
+                .map(|(key, value)| (key.clone(), render_json(value, change)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::subscriber::DefaultGuard;
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// In-process http server serving scripted responses and recording all requests
+#[derive(Clone)]
+pub struct StandIn {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    responses: HashMap<String, Response>,
+    requests: Vec<Request>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is not json")
+    }
+}
+
+impl StandIn {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stand_in = StandIn {
+            addr: listener.local_addr().unwrap(),
+            state: Arc::default(),
+        };
+        let server = stand_in.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let server = server.clone();
+                tokio::spawn(async move { server.handle(stream).await });
+            }
+        });
+        stand_in
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Set the response for a path, used for all following requests, unknown paths return a 404
+    pub fn respond(&self, path: &str, response: Response) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .insert(path.into(), response);
+    }
+
+    pub fn requests(&self, path: &str) -> Vec<Request> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.path == path)
+            .cloned()
+            .collect()
+    }
+
+    /// All requests with a path starting with the prefix, for paths containing generated parts
+    pub fn requests_under(&self, prefix: &str) -> Vec<Request> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|request| request.path.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    async fn handle(&self, stream: TcpStream) {
+        let (read, mut write) = stream.into_split();
+        let mut reader = BufReader::new(read);
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+        }
+        let length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await.unwrap();
+
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(Request {
+                method,
+                path: path.clone(),
+                headers,
+                body: String::from_utf8_lossy(&body).into(),
+            });
+            state
+                .responses
+                .get(&path)
+                .cloned()
+                .unwrap_or_else(|| Response::status(404))
+        };
+
+        let mut head = format!(
+            "HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\nConnection: close\r\n",
+            response.status,
+            response.body.len()
+        );
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        write.write_all(head.as_bytes()).await.unwrap();
+        write.write_all(response.body.as_bytes()).await.unwrap();
+        write.shutdown().await.ok();
+    }
+}
+
+/// Build an rss feed from (guid, title) pairs, newest first
+pub fn rss(items: &[(&str, &str)]) -> String {
+    rss_channel(items.iter().map(|(guid, title)| {
+        format!(
+            "<item><title>{}</title><guid>{}</guid><link>https://example.com/{}</link></item>",
+            title, guid, guid
+        )
+    }))
+}
+
+/// Build an rss feed from the xml of the items, newest first
+pub fn rss_channel(items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    let items: String = items
+        .into_iter()
+        .map(|item| item.as_ref().to_string())
+        .collect();
+    format!(
+        r#"<?xml version="1.0"?><rss version="2.0"><channel><title>feed</title><link>https://example.com</link><description>feed</description>{}</channel></rss>"#,
+        items
+    )
+}
+
+/// Log output captured as json lines, in the format of `log_format = "json"`
+#[derive(Clone, Default)]
+pub struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    /// Capture everything logged on the current thread, until the guard is dropped
+    pub fn capture() -> (Logs, DefaultGuard) {
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_max_level(Level::DEBUG)
+            .with_writer(logs.clone())
+            .finish();
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+
+    /// All captured events with the given message
+    pub fn events(&self, message: &str) -> Vec<serde_json::Map<String, serde_json::Value>> {
+        self.text()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("log line is not json"))
+            .filter_map(|event: serde_json::Value| match event {
+                serde_json::Value::Object(event) if event["message"] == message => Some(event),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Logs {
+        self.clone()
+    }
+}
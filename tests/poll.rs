@@ -0,0 +1,1542 @@
+mod common;
+
+use async_trait::async_trait;
+use chrono::{NaiveTime, TimeDelta};
+use common::{rss, rss_channel, Logs, Response, StandIn};
+use hmac::{Hmac, Mac};
+use rss_webhook_trigger::action::{Action, ActionContext, ActionRegistry, Delivery};
+use rss_webhook_trigger::change::ChangeEvent;
+use rss_webhook_trigger::client::ClientPool;
+use rss_webhook_trigger::config::Config;
+use rss_webhook_trigger::error::ActionError;
+use rss_webhook_trigger::history::{self, History};
+use rss_webhook_trigger::runner::Runner;
+use rss_webhook_trigger::source::SourceRegistry;
+use rss_webhook_trigger::Clock;
+use serde_json::json;
+use sha2::Sha256;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const FEED: &str = "/feed.xml";
+const HOOK: &str = "/hook";
+
+/// Runner polling the stand-in feed every minute, posting the version and items to the stand-in hook
+async fn setup(extra: &str) -> (StandIn, Runner, Clock) {
+    setup_with_body(r#"{ version = "{title}", items = "{items}" }"#, extra).await
+}
+
+async fn setup_with_body(body: &str, extra: &str) -> (StandIn, Runner, Clock) {
+    setup_runner(
+        &format!(
+            r#"
+            hook = "{{base}}{}"
+            body = {}
+            template = true
+            {}
+            "#,
+            HOOK, body, extra
+        ),
+        ActionRegistry::default(),
+        None,
+    )
+    .await
+}
+
+/// Runner polling the stand-in feed every minute with the given feed config
+///
+/// `{base}` in the feed config is replaced by the url of the stand-in
+async fn setup_runner(
+    feed: &str,
+    actions: ActionRegistry,
+    history: Option<&Path>,
+) -> (StandIn, Runner, Clock) {
+    let stand_in = StandIn::start().await;
+    let feed = feed.replace("{base}", &stand_in.url(""));
+    stand_in.respond(HOOK, Response::ok(""));
+    let history = history.map_or(String::new(), |path| {
+        format!("[history]\npath = {:?}", path)
+    });
+    let config = Config::from_toml(&format!(
+        r#"
+        interval = 60
+        min_interval = 10
+        {}
+
+        [[feed]]
+        feed = "{}"
+        {}
+        "#,
+        history,
+        stand_in.url(FEED),
+        feed
+    ))
+    .unwrap();
+    let history = config
+        .history
+        .as_ref()
+        .map(|history| History::open(history).unwrap());
+    let clock = Clock::default();
+    let clients = ClientPool::new(&config).unwrap();
+    let runner = Runner::new(config, clients, SourceRegistry::default(), actions, history)
+        .unwrap()
+        .with_clock(clock.clone());
+    (stand_in, runner, clock)
+}
+
+/// Titles of the items posted to the hook
+fn titles(request: &common::Request) -> Vec<String> {
+    request.json()["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_trigger_on_new_item() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+
+    // the first observation of a feed doesn't trigger
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    // not fetched again before the interval passed
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(FEED).len());
+
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("3", "v3"), ("2", "v2"), ("1", "v1")])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("POST", hooks[0].method);
+    assert_eq!("v3", hooks[0].json()["version"]);
+    assert_eq!(vec!["v3", "v2"], titles(&hooks[0]));
+
+    // unchanged feed doesn't trigger again
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+    assert_eq!(1, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_body_not_templated_by_default() {
+    let (stand_in, mut runner, clock) = setup_runner(
+        r#"
+        hook = "{base}/hook"
+        body = { message = "{title} is literal" }
+        "#,
+        ActionRegistry::default(),
+        None,
+    )
+    .await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("{title} is literal", hooks[0].json()["message"]);
+}
+
+fn hmac(payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Trigger the hook once and return the request
+async fn signed_request(signature: &str) -> common::Request {
+    let (stand_in, mut runner, clock) = setup(&format!("signature = {}", signature)).await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    stand_in.requests(HOOK).remove(0)
+}
+
+#[tokio::test]
+async fn test_signature_github_style() {
+    let request = signed_request(r#"{ secret = "secret" }"#).await;
+    assert_eq!(None, request.header("x-hub-signature-timestamp"));
+    assert_eq!(
+        Some(hmac(&request.body).as_str()),
+        request.header("x-hub-signature-256")
+    );
+}
+
+#[tokio::test]
+async fn test_signature_timestamp() {
+    let request = signed_request(r#"{ secret = "secret", header = "X-Signature" }"#).await;
+    let timestamp = request.header("x-hub-signature-timestamp").unwrap();
+    assert_eq!(
+        Some(hmac(&format!("{}.{}", timestamp, request.body)).as_str()),
+        request.header("x-signature")
+    );
+}
+
+#[tokio::test]
+async fn test_feed_auth() {
+    let (stand_in, mut runner, _clock) = setup(
+        r#"
+        feed_headers = { X-Api-Key = "feed-key" }
+        basic_auth = { username = "me", password = "feed-password" }
+        "#,
+    )
+    .await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    let request = stand_in.requests(FEED).remove(0);
+    assert_eq!(Some("feed-key"), request.header("x-api-key"));
+    assert_eq!(
+        Some("Basic bWU6ZmVlZC1wYXNzd29yZA=="),
+        request.header("authorization")
+    );
+}
+
+#[tokio::test]
+async fn test_feed_bearer_token() {
+    let (stand_in, mut runner, _clock) = setup(r#"bearer_token = "feed-token""#).await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    let request = stand_in.requests(FEED).remove(0);
+    assert_eq!(Some("Bearer feed-token"), request.header("authorization"));
+}
+
+#[tokio::test]
+async fn test_not_modified() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("1", "v1")])).header("ETag", "\"v1\""),
+    );
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::status(304).header("ETag", "\"v1\""));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    let requests = stand_in.requests(FEED);
+    assert_eq!(2, requests.len());
+    assert_eq!(None, requests[0].header("if-none-match"));
+    assert_eq!(Some("\"v1\""), requests[1].header("if-none-match"));
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_rate_limited() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::status(429).header("Retry-After", "300"));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+
+    // the normal interval passed, but the server asked us to wait longer
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+
+    clock.advance(Duration::from_secs(250));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+    assert_eq!(1, stand_in.requests(HOOK).len());
+}
+
+/// Poll the feed after the given number of seconds, returning the total number of fetches
+async fn fetches_after(
+    stand_in: &StandIn,
+    runner: &mut Runner,
+    clock: &Clock,
+    seconds: u64,
+) -> usize {
+    clock.advance(Duration::from_secs(seconds));
+    runner.tick().await;
+    stand_in.requests(FEED).len()
+}
+
+#[tokio::test]
+async fn test_retry_after_date() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(
+        FEED,
+        Response::status(429)
+            .header("Date", "Mon, 01 Jan 2024 00:00:00 GMT")
+            .header("Retry-After", "Mon, 01 Jan 2024 00:05:00 GMT"),
+    );
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 290).await);
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+}
+
+#[tokio::test]
+async fn test_service_unavailable() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    // a 503 with Retry-After is a request to wait, not a failure
+    stand_in.respond(FEED, Response::status(503).header("Retry-After", "300"));
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 61).await);
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 290).await);
+    assert_eq!(3, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+    assert_eq!(1, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_cache_control_max_age() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("1", "v1")])).header("Cache-Control", "public, max-age=300"),
+    );
+    runner.tick().await;
+
+    // not polled again before the response is stale
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 290).await);
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+}
+
+#[tokio::test]
+async fn test_cache_control_no_cache() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("1", "v1")]))
+            .header("Cache-Control", "no-cache, max-age=300")
+            .header("Expires", "Mon, 01 Jan 2024 01:00:00 GMT")
+            .header("Date", "Mon, 01 Jan 2024 00:00:00 GMT"),
+    );
+    runner.tick().await;
+
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 61).await);
+}
+
+#[tokio::test]
+async fn test_expires() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("1", "v1")]))
+            .header("Date", "Mon, 01 Jan 2024 00:00:00 GMT")
+            .header("Expires", "Mon, 01 Jan 2024 00:05:00 GMT"),
+    );
+    runner.tick().await;
+
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 290).await);
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+}
+
+#[tokio::test]
+async fn test_server_delay_limits() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    // asking for a retry sooner than `min_interval` waits for the minimum interval
+    stand_in.respond(FEED, Response::status(429).header("Retry-After", "1"));
+    runner.tick().await;
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 5).await);
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("1", "v1")])).header("Cache-Control", "max-age=31536000"),
+    );
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 6).await);
+
+    // a freshness of a year is limited to `max_interval`, which defaults to a day
+    assert_eq!(
+        2,
+        fetches_after(&stand_in, &mut runner, &clock, 86390).await
+    );
+    assert_eq!(3, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+}
+
+/// Rss feed asking to be polled at most every 10 minutes
+fn rss_with_ttl(items: &[(&str, &str)]) -> String {
+    rss(items).replace("</description>", "</description><ttl>10</ttl>")
+}
+
+#[tokio::test]
+async fn test_feed_hints() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss_with_ttl(&[("1", "v1")])));
+    runner.tick().await;
+
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 590).await);
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 20).await);
+}
+
+#[tokio::test]
+async fn test_ignore_feed_hints() {
+    let (stand_in, mut runner, clock) = setup("ignore_hints = true").await;
+    stand_in.respond(FEED, Response::ok(rss_with_ttl(&[("1", "v1")])));
+    runner.tick().await;
+
+    assert_eq!(2, fetches_after(&stand_in, &mut runner, &clock, 61).await);
+}
+
+#[tokio::test]
+async fn test_failure_backoff() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::status(500));
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(FEED).len());
+
+    // the first retry is after the normal interval
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+
+    // after the second failure the interval is doubled
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+    clock.advance(Duration::from_secs(60));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+
+    // recovering is treated as the first observation
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    clock.advance(Duration::from_secs(241));
+    runner.tick().await;
+    assert_eq!(4, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_debounce() {
+    let (stand_in, mut runner, clock) = setup(
+        r#"
+        debounce = 120
+
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/first"
+        body = { items = "{items}" }
+        template = true
+        debounce = 120
+        items = "first"
+
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/last"
+        body = { items = "{items}" }
+        template = true
+        debounce = 120
+        items = "last"
+        "#,
+    )
+    .await;
+    stand_in.respond("/first", Response::ok(""));
+    stand_in.respond("/last", Response::ok(""));
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    // changes within the window are collapsed into the pending trigger
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("3", "v3"), ("2", "v2"), ("1", "v1")])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!(vec!["v3", "v2"], titles(&hooks[0]));
+    let first = stand_in.requests("/first");
+    assert_eq!(1, first.len());
+    assert_eq!(vec!["v2"], titles(&first[0]));
+    let last = stand_in.requests("/last");
+    assert_eq!(1, last.len());
+    assert_eq!(vec!["v3"], titles(&last[0]));
+}
+
+/// Move the clock forward to the next time the local time is `hour:minute`
+fn advance_to(clock: &Clock, hour: u32, minute: u32) {
+    let target = NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+    let mut until = target - clock.now_local().time();
+    if until <= TimeDelta::zero() {
+        until += TimeDelta::days(1);
+    }
+    clock.advance(until.to_std().unwrap());
+}
+
+#[tokio::test]
+async fn test_quiet_window() {
+    let (stand_in, mut runner, clock) =
+        setup(r#"quiet = { start = "11:00", end = "13:00" }"#).await;
+    advance_to(&clock, 12, 0);
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    clock.advance(Duration::from_secs(50 * 60));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    // the held back trigger is delivered once the window ends
+    clock.advance(Duration::from_secs(10 * 60));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!(vec!["v2"], titles(&hooks[0]));
+}
+
+#[tokio::test]
+async fn test_quiet_window_over_midnight() {
+    let (stand_in, mut runner, clock) =
+        setup(r#"quiet = { start = "22:00", end = "01:00" }"#).await;
+    advance_to(&clock, 21, 0);
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    // outside of the window triggers are delivered right away
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(HOOK).len());
+
+    advance_to(&clock, 23, 30);
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("3", "v3"), ("2", "v2"), ("1", "v1")])),
+    );
+    runner.tick().await;
+    advance_to(&clock, 0, 30);
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(HOOK).len());
+
+    advance_to(&clock, 1, 0);
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(2, hooks.len());
+    assert_eq!(vec!["v3"], titles(&hooks[1]));
+}
+
+#[tokio::test]
+async fn test_cron() {
+    let (stand_in, mut runner, clock) = setup(r#"cron = "0 * * * *""#).await;
+    advance_to(&clock, 10, 30);
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+
+    // the first fetch waits for the first matching time
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(FEED).len());
+    assert_eq!(
+        0,
+        fetches_after(&stand_in, &mut runner, &clock, 29 * 60).await
+    );
+    assert_eq!(
+        1,
+        fetches_after(&stand_in, &mut runner, &clock, 2 * 60).await
+    );
+
+    // after that the normal interval doesn't apply
+    assert_eq!(1, fetches_after(&stand_in, &mut runner, &clock, 61).await);
+    assert_eq!(
+        1,
+        fetches_after(&stand_in, &mut runner, &clock, 57 * 60).await
+    );
+    assert_eq!(
+        2,
+        fetches_after(&stand_in, &mut runner, &clock, 2 * 60).await
+    );
+}
+
+fn dated_rss(items: &[(&str, &str)]) -> String {
+    rss_channel(items.iter().map(|(date, title)| {
+        format!(
+            "<item><title>{}</title><pubDate>{}</pubDate></item>",
+            title, date
+        )
+    }))
+}
+
+#[tokio::test]
+async fn test_key_without_guid() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    let first = "Mon, 01 Jan 2024 00:00:00 +0000";
+    let second = "Tue, 02 Jan 2024 00:00:00 +0000";
+    stand_in.respond(FEED, Response::ok(dated_rss(&[(first, "v1")])));
+    runner.tick().await;
+
+    // without guid the pubDate is the key, so a changed title alone isn't an update
+    stand_in.respond(FEED, Response::ok(dated_rss(&[(first, "v1 (edited)")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    stand_in.respond(
+        FEED,
+        Response::ok(dated_rss(&[(second, "v2"), (first, "v1 (edited)")])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("v2", hooks[0].json()["version"]);
+}
+
+#[tokio::test]
+async fn test_missing_key() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    // items without guid, pubDate or link can't be tracked
+    stand_in.respond(
+        FEED,
+        Response::ok(rss_channel([r#"<item><title>v2</title></item>"#])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_skip_later_item_without_key() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(
+        FEED,
+        Response::ok(rss_channel([
+            r#"<item><title>v3</title><guid>3</guid></item>"#,
+            r#"<item><title>v2</title></item>"#,
+            r#"<item><title>v1</title><guid>1</guid></item>"#,
+        ])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    let items = hooks[0].json()["items"].as_array().unwrap().clone();
+    assert_eq!(1, items.len());
+    assert_eq!("v3", items[0]["title"]);
+}
+
+#[tokio::test]
+async fn test_on_start_trigger() {
+    let (stand_in, mut runner, _clock) = setup(r#"on_start = "trigger""#).await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    runner.tick().await;
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("v2", hooks[0].json()["version"]);
+    assert_eq!(1, hooks[0].json()["items"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn test_on_start_trigger_if_newer_than() {
+    let (stand_in, mut runner, _clock) =
+        setup(r#"on_start = { trigger_if_newer_than = "2024-01-01T12:00:00Z" }"#).await;
+    stand_in.respond(
+        FEED,
+        Response::ok(dated_rss(&[
+            ("Wed, 03 Jan 2024 00:00:00 +0000", "v3"),
+            ("Tue, 02 Jan 2024 00:00:00 +0000", "v2"),
+            ("Mon, 01 Jan 2024 00:00:00 +0000", "v1"),
+        ])),
+    );
+    runner.tick().await;
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!(vec!["v3", "v2"], titles(&hooks[0]));
+}
+
+#[tokio::test]
+async fn test_on_start_nothing_newer() {
+    let (stand_in, mut runner, _clock) =
+        setup(r#"on_start = { trigger_if_newer_than = "2024-06-01T00:00:00Z" }"#).await;
+    stand_in.respond(
+        FEED,
+        Response::ok(dated_rss(&[("Mon, 01 Jan 2024 00:00:00 +0000", "v1")])),
+    );
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_change_event() {
+    let (stand_in, mut runner, clock) = setup_with_body(r#""{change}""#, "").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    let change = hooks[0].json();
+    assert_eq!("feed", change["source"]);
+    assert_eq!(stand_in.url(FEED), change["feed"]);
+    assert_eq!("v2", change["items"][0]["title"]);
+    let old_key = change["old_key"].as_str().unwrap();
+    let new_key = change["new_key"].as_str().unwrap();
+    assert!(old_key.starts_with("v1:"));
+    assert_ne!(old_key, new_key);
+}
+
+#[tokio::test]
+async fn test_detect_edits() {
+    const EDITS: &str = "/edits";
+    let (stand_in, mut runner, clock) = setup(
+        r#"
+        detect_edits = true
+
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/edits"
+        body = "{change}"
+        template = true
+        trigger_on = ["updated"]
+        "#,
+    )
+    .await;
+    stand_in.respond(EDITS, Response::ok(""));
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    runner.tick().await;
+
+    // an edit to an existing item only triggers the action for edits
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1 (fixed)")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+    let edits = stand_in.requests(EDITS);
+    assert_eq!(1, edits.len());
+    let change = edits[0].json();
+    assert_eq!("updated", change["kind"]);
+    assert_eq!("v1 (fixed)", change["items"][0]["title"]);
+    assert_eq!(1, change["items"].as_array().unwrap().len());
+
+    // a new item only triggers the default hook
+    stand_in.respond(
+        FEED,
+        Response::ok(rss(&[("3", "v3"), ("2", "v2"), ("1", "v1 (fixed)")])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(HOOK).len());
+    assert_eq!(1, stand_in.requests(EDITS).len());
+}
+
+#[tokio::test]
+async fn test_edits_ignored_by_default() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1 (fixed)")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(2, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+fn linked_rss(items: &[(&str, &str)]) -> String {
+    rss_channel(
+        items.iter().map(|(link, title)| {
+            format!("<item><title>{}</title><link>{}</link></item>", title, link)
+        }),
+    )
+}
+
+#[tokio::test]
+async fn test_item_key_normalize() {
+    let (stand_in, mut runner, clock) =
+        setup(r#"item_key = { fields = ["link"], normalize = ["strip_query", "strip_fragment"] }"#)
+            .await;
+    stand_in.respond(
+        FEED,
+        Response::ok(linked_rss(&[("https://example.com/1?session=a", "v1")])),
+    );
+    runner.tick().await;
+
+    // only the query changed, so this is the same item
+    stand_in.respond(
+        FEED,
+        Response::ok(linked_rss(&[("https://example.com/1?session=b#top", "v1")])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    stand_in.respond(
+        FEED,
+        Response::ok(linked_rss(&[
+            ("https://example.com/2?session=c", "v2"),
+            ("https://example.com/1?session=c", "v1"),
+        ])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!(1, hooks[0].json()["items"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn test_item_key_fields() {
+    // the guid is reused for every item, so identify items by title and link instead
+    let (stand_in, mut runner, clock) = setup(r#"item_key = { fields = ["title", "link"] }"#).await;
+    let feed = |items: &[&str]| {
+        rss_channel(items.iter().map(|title| {
+            format!(
+                "<item><title>{}</title><guid>same</guid><link>https://example.com/{}</link></item>",
+                title, title
+            )
+        }))
+    };
+    stand_in.respond(FEED, Response::ok(feed(&["v1"])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(feed(&["v2", "v1"])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("v2", hooks[0].json()["version"]);
+}
+
+#[test]
+fn test_item_key_without_fields() {
+    let config = r#"
+        [[feed]]
+        feed = "https://example.com/feed.xml"
+        hook = "https://example.com/hook"
+        item_key = { fields = [] }
+        "#;
+    assert!(Config::from_toml(config).is_err());
+}
+
+#[tokio::test]
+async fn test_item_key_missing() {
+    let (stand_in, mut runner, clock) = setup(
+        r#"
+        item_key = { fields = ["enclosure"] }
+        on_start = "trigger"
+        "#,
+    )
+    .await;
+    // without enclosure the items can't be identified
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    stand_in.respond(
+        FEED,
+        Response::ok(rss_channel([
+            r#"<item><title>v1</title><enclosure url="https://example.com/v1.mp3" length="1" type="audio/mpeg"/></item>"#,
+        ])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(1, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_empty_feed() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!(2, hooks[0].json()["items"].as_array().unwrap().len());
+
+    // items that were seen before the feed was emptied don't trigger again
+    stand_in.respond(FEED, Response::ok(rss(&[])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(4, stand_in.requests(FEED).len());
+    assert_eq!(1, stand_in.requests(HOOK).len());
+}
+
+#[tokio::test]
+async fn test_recover_from_error_page() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    runner.tick().await;
+
+    stand_in.respond(FEED, Response::ok("<html>Internal error</html>"));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    // the newest item was removed while the feed was broken, the remaining items were already seen
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    stand_in.respond(FEED, Response::ok(rss(&[("3", "v3"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    assert_eq!("v3", hooks[0].json()["version"]);
+}
+
+#[tokio::test]
+async fn test_removed_item() {
+    let (stand_in, mut runner, clock) = setup("").await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    runner.tick().await;
+
+    // the feed changed, but the remaining item was already seen
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    // same for reordering the items
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1"), ("2", "v2")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(3, stand_in.requests(FEED).len());
+    assert_eq!(0, stand_in.requests(HOOK).len());
+}
+
+fn podcast(items: &[(&str, Option<&str>)]) -> String {
+    rss_channel(items.iter().map(|(guid, mime_type)| {
+        let enclosure = mime_type.map_or(String::new(), |mime_type| {
+            format!(
+                r#"<enclosure url="https://example.com/{}" length="100" type="{}"/>"#,
+                guid, mime_type
+            )
+        });
+        format!(
+            "<item><title>{}</title><guid>{}</guid>{}</item>",
+            guid, guid, enclosure
+        )
+    }))
+}
+
+#[tokio::test]
+async fn test_enclosure_types() {
+    let (stand_in, mut runner, clock) = setup(r#"enclosure_types = ["audio/*"]"#).await;
+    stand_in.respond(FEED, Response::ok(podcast(&[("1", Some("audio/mpeg"))])));
+    runner.tick().await;
+
+    // items without a matching enclosure are ignored
+    stand_in.respond(
+        FEED,
+        Response::ok(podcast(&[
+            ("3", None),
+            ("2", Some("video/mp4")),
+            ("1", Some("audio/mpeg")),
+        ])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    assert_eq!(0, stand_in.requests(HOOK).len());
+
+    stand_in.respond(
+        FEED,
+        Response::ok(podcast(&[
+            ("4", Some("audio/ogg")),
+            ("3", None),
+            ("2", Some("video/mp4")),
+            ("1", Some("audio/mpeg")),
+        ])),
+    );
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    let hooks = stand_in.requests(HOOK);
+    assert_eq!(1, hooks.len());
+    let items = hooks[0].json()["items"].clone();
+    assert_eq!(1, items.as_array().unwrap().len());
+    assert_eq!("https://example.com/4", items[0]["enclosures"][0]["url"]);
+    assert_eq!("audio/ogg", items[0]["enclosures"][0]["mime_type"]);
+    assert_eq!(100, items[0]["enclosures"][0]["length"]);
+}
+
+/// Trigger a single action for a new item and return the stand-in that received the request
+///
+/// `{base}` in the action config is replaced by the url of the stand-in
+async fn trigger_action(action: &str) -> StandIn {
+    let (stand_in, mut runner, clock) = setup_runner(
+        &format!("[[feed.action]]\n{}", action),
+        ActionRegistry::default(),
+        None,
+    )
+    .await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    stand_in
+}
+
+#[tokio::test]
+async fn test_discord() {
+    let stand_in = trigger_action(
+        r#"
+        type = "discord"
+        webhook = "{base}/discord"
+        username = "releases"
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/discord");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        json!({
+            "username": "releases",
+            "embeds": [{ "title": "v2", "url": "https://example.com/2" }],
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_slack() {
+    let stand_in = trigger_action(
+        r#"
+        type = "slack"
+        webhook = "{base}/slack"
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/slack");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        json!({ "text": "<https://example.com/2|v2>" }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_matrix() {
+    let stand_in = trigger_action(
+        r#"
+        type = "matrix"
+        homeserver = "{base}"
+        room = "!room:example.com"
+        access_token = "matrix-token"
+        notice = true
+        "#,
+    )
+    .await;
+    // the last segment is a unique transaction id
+    let requests =
+        stand_in.requests_under("/_matrix/client/v3/rooms/!room:example.com/send/m.room.message/");
+    assert_eq!(1, requests.len());
+    assert_eq!("PUT", requests[0].method);
+    assert_eq!(
+        Some("Bearer matrix-token"),
+        requests[0].header("authorization")
+    );
+    assert_eq!(
+        json!({
+            "msgtype": "m.notice",
+            "body": "v2\nhttps://example.com/2",
+            "format": "org.matrix.custom.html",
+            "formatted_body": "<a href=\"https://example.com/2\">v2</a>",
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_ntfy() {
+    let stand_in = trigger_action(
+        r#"
+        type = "ntfy"
+        server = "{base}/ntfy"
+        topic = "releases"
+        token = "ntfy-token"
+        priority = 4
+        tags = ["tada"]
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/ntfy");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        Some("Bearer ntfy-token"),
+        requests[0].header("authorization")
+    );
+    assert_eq!(
+        json!({
+            "topic": "releases",
+            "title": "v2",
+            "message": "https://example.com/2",
+            "click": "https://example.com/2",
+            "priority": 4,
+            "tags": ["tada"],
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_gotify() {
+    let stand_in = trigger_action(
+        r#"
+        type = "gotify"
+        server = "{base}/gotify/"
+        token = "gotify-token"
+        priority = 5
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/gotify/message");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(Some("gotify-token"), requests[0].header("x-gotify-key"));
+    assert_eq!(
+        json!({
+            "title": "v2",
+            "message": "https://example.com/2",
+            "priority": 5,
+            "extras": {
+                "client::notification": { "click": { "url": "https://example.com/2" } }
+            },
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_github_repository_dispatch() {
+    let stand_in = trigger_action(
+        r#"
+        type = "github"
+        api = "{base}"
+        repository = "owner/repo"
+        token = "github-token"
+        inputs = { channel = "stable" }
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/repos/owner/repo/dispatches");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        Some("Bearer github-token"),
+        requests[0].header("authorization")
+    );
+    assert_eq!(
+        Some("application/vnd.github+json"),
+        requests[0].header("accept")
+    );
+    assert_eq!(
+        json!({
+            "event_type": "feed-update",
+            "client_payload": {
+                "feed": stand_in.url(FEED),
+                "version": "v2",
+                "link": "https://example.com/2",
+                "channel": "stable",
+            },
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_github_workflow_dispatch() {
+    let stand_in = trigger_action(
+        r#"
+        type = "github"
+        api = "{base}"
+        repository = "owner/repo"
+        token = "github-token"
+        workflow = "build.yml"
+        ref = "release"
+        inputs = { version = "{title}" }
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/repos/owner/repo/actions/workflows/build.yml/dispatches");
+    assert_eq!(1, requests.len());
+    assert_eq!(
+        Some("Bearer github-token"),
+        requests[0].header("authorization")
+    );
+    assert_eq!(
+        json!({ "ref": "release", "inputs": { "version": "v2" } }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_gitlab() {
+    let stand_in = trigger_action(
+        r#"
+        type = "gitlab"
+        server = "{base}"
+        project = "group/project"
+        token = "gitlab-token"
+        variables = { UPSTREAM_VERSION = "{title}" }
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/api/v4/projects/group%2Fproject/trigger/pipeline");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        Some("application/x-www-form-urlencoded"),
+        requests[0].header("content-type")
+    );
+    let mut form: Vec<(String, String)> = url::form_urlencoded::parse(requests[0].body.as_bytes())
+        .into_owned()
+        .collect();
+    form.sort();
+    let expected = [
+        ("ref", "main".to_string()),
+        ("token", "gitlab-token".into()),
+        ("variables[RSS_FEED]", stand_in.url(FEED)),
+        ("variables[RSS_ITEM_LINK]", "https://example.com/2".into()),
+        ("variables[RSS_ITEM_TITLE]", "v2".into()),
+        ("variables[UPSTREAM_VERSION]", "v2".into()),
+    ]
+    .map(|(key, value)| (key.to_string(), value));
+    assert_eq!(expected.to_vec(), form);
+}
+
+#[tokio::test]
+async fn test_woodpecker() {
+    let stand_in = trigger_action(
+        r#"
+        type = "woodpecker"
+        server = "{base}"
+        repo_id = 12
+        token = "woodpecker-token"
+        variables = { RSS_ITEM_TITLE = "release {title}" }
+        "#,
+    )
+    .await;
+    let requests = stand_in.requests("/api/repos/12/pipelines");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        Some("Bearer woodpecker-token"),
+        requests[0].header("authorization")
+    );
+    // configured variables take precedence over the item variables
+    assert_eq!(
+        json!({
+            "branch": "main",
+            "variables": {
+                "RSS_FEED": stand_in.url(FEED),
+                "RSS_ITEM_TITLE": "release v2",
+                "RSS_ITEM_LINK": "https://example.com/2",
+            },
+        }),
+        requests[0].json()
+    );
+}
+
+#[tokio::test]
+async fn test_forgejo() {
+    let stand_in = trigger_action(
+        r#"
+        type = "forgejo"
+        server = "{base}"
+        repository = "owner/repo"
+        workflow = "build.yml"
+        token = "forgejo-token"
+        inputs = { version = "{title}" }
+        "#,
+    )
+    .await;
+    let requests =
+        stand_in.requests("/api/v1/repos/owner/repo/actions/workflows/build.yml/dispatches");
+    assert_eq!(1, requests.len());
+    assert_eq!("POST", requests[0].method);
+    assert_eq!(
+        Some("token forgejo-token"),
+        requests[0].header("authorization")
+    );
+    assert_eq!(
+        json!({ "ref": "main", "inputs": { "version": "v2" } }),
+        requests[0].json()
+    );
+}
+
+/// Custom action remembering the titles of the items it was triggered for
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Vec<String>>>>);
+
+#[async_trait]
+impl Action for Recorder {
+    async fn trigger(
+        &self,
+        _context: &ActionContext,
+        change: &ChangeEvent,
+    ) -> Result<Option<Delivery>, ActionError> {
+        let titles = change
+            .items
+            .iter()
+            .map(|item| item.display_title().to_string())
+            .collect();
+        self.0.lock().unwrap().push(titles);
+        Ok(None)
+    }
+}
+
+#[tokio::test]
+async fn test_custom_action() {
+    let recorder = Recorder::default();
+    let mut actions = ActionRegistry::default();
+    actions.register("recorder", recorder.clone());
+    let (stand_in, mut runner, clock) = setup_runner(
+        r#"
+        [[feed.action]]
+        type = "custom"
+        name = "recorder"
+        "#,
+        actions,
+        None,
+    )
+    .await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+
+    assert_eq!(vec![vec!["v2".to_string()]], *recorder.0.lock().unwrap());
+}
+
+#[test]
+fn test_unknown_custom_action() {
+    let config = Config::from_toml(
+        r#"
+        [[feed]]
+        feed = "https://example.com/feed.xml"
+
+        [[feed.action]]
+        type = "custom"
+        name = "recorder"
+        "#,
+    )
+    .unwrap();
+    let clients = ClientPool::new(&config).unwrap();
+    let runner = Runner::new(
+        config,
+        clients,
+        SourceRegistry::default(),
+        ActionRegistry::default(),
+        None,
+    );
+    assert!(matches!(runner, Err(ActionError::UnknownAction(name)) if name == "recorder"));
+}
+
+#[tokio::test]
+async fn test_exec_runs_in_background() {
+    let dir = std::env::temp_dir().join(format!("rss-webhook-trigger-exec-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("history.jsonl");
+    let release = dir.join("release");
+    // the command only finishes once the test allows it to
+    let (stand_in, mut runner, clock) = setup_runner(
+        &format!(
+            r#"
+            [[feed.action]]
+            type = "exec"
+            command = "sh"
+            args = ["-c", "while [ ! -e '{}' ]; do sleep 0.01; done; exit 3"]
+            "#,
+            release.display()
+        ),
+        ActionRegistry::default(),
+        Some(&path),
+    )
+    .await;
+
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    // polling isn't held up by the running command
+    tokio::time::timeout(Duration::from_secs(30), runner.tick())
+        .await
+        .expect("polling waited for the command");
+    assert!(history::read(&path).unwrap().is_empty());
+
+    // the outcome is recorded once the command finishes
+    std::fs::write(&release, "").unwrap();
+    let mut entries = Vec::new();
+    for _ in 0..100 {
+        entries = history::read(&path).unwrap();
+        if !entries.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(1, entries.len());
+    assert_eq!("failed", entries[0].status);
+    assert_eq!(Some("exit_status"), entries[0].error_kind.as_deref());
+}
+
+#[tokio::test]
+async fn test_logs_hide_secrets() {
+    let (logs, _guard) = Logs::capture();
+    let (stand_in, mut runner, clock) = setup_runner(
+        r#"
+        basic_auth = { username = "me", password = "feed-password" }
+
+        [[feed.action]]
+        type = "discord"
+        webhook = "{base}/discord/discord-token"
+
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/hook"
+        headers = { Authorization = "Bearer hook-token" }
+
+        [[feed.action]]
+        type = "exec"
+        command = "true"
+        env = { API_TOKEN = "exec-token" }
+        "#,
+        ActionRegistry::default(),
+        None,
+    )
+    .await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    // the command runs in the background
+    for _ in 0..100 {
+        if logs.events("action triggered").len() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // the discord hook isn't known to the stand-in, so the failure is logged including its url
+    assert_eq!(3, logs.events("action triggered").len());
+    assert_eq!(1, logs.events("running command").len());
+    assert_eq!(2, logs.events("sending trigger request").len());
+    let output = logs.text();
+    for secret in ["feed-password", "discord-token", "hook-token", "exec-token"] {
+        assert!(!output.contains(secret), "{} was logged", secret);
+    }
+}
+
+#[tokio::test]
+async fn test_log_fields() {
+    let (logs, _guard) = Logs::capture();
+    let (stand_in, mut runner, clock) = setup_runner(
+        r#"
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/hook"
+
+        [[feed.action]]
+        type = "webhook"
+        hook = "{base}/missing"
+        "#,
+        ActionRegistry::default(),
+        None,
+    )
+    .await;
+    stand_in.respond(FEED, Response::ok(rss(&[("1", "v1")])));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::status(500));
+    clock.advance(Duration::from_secs(61));
+    runner.tick().await;
+    stand_in.respond(FEED, Response::ok(rss(&[("2", "v2"), ("1", "v1")])));
+    clock.advance(Duration::from_secs(3600));
+    runner.tick().await;
+
+    let fetches = logs.events("feed fetched");
+    let statuses: Vec<_> = fetches.iter().map(|event| &event["status"]).collect();
+    assert_eq!(vec!["new", "failed", "updated"], statuses);
+    for event in &fetches {
+        let mut fields: Vec<_> = event.keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            vec![
+                "backoff_secs",
+                "duration_ms",
+                "edited_items",
+                "error",
+                "error_kind",
+                "failures",
+                "feed",
+                "http_status",
+                "level",
+                "message",
+                "new_items",
+                "new_key",
+                "old_key",
+                "parse_failures",
+                "source",
+                "status",
+                "target",
+                "timestamp",
+            ],
+            fields
+        );
+    }
+    assert_eq!(json!(500), fetches[1]["http_status"]);
+    assert_eq!(json!(1), fetches[1]["failures"]);
+    assert_eq!(json!(""), fetches[0]["error"]);
+    assert_eq!(json!(1), fetches[2]["new_items"]);
+
+    let triggers = logs.events("action triggered");
+    let statuses: Vec<_> = triggers.iter().map(|event| &event["status"]).collect();
+    assert_eq!(vec!["success", "failed"], statuses);
+    let fields = |event: &serde_json::Map<_, _>| {
+        let mut fields: Vec<String> = event.keys().cloned().collect();
+        fields.sort_unstable();
+        fields
+    };
+    assert_eq!(fields(&triggers[0]), fields(&triggers[1]));
+    assert_eq!(json!(""), triggers[0]["error_kind"]);
+    assert_eq!(json!(404), triggers[1]["http_status"]);
+}